@@ -0,0 +1,1617 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use anyhow::{anyhow, bail, Context, Result};
+use embedded_hal::digital::OutputPin;
+
+mod baud;
+mod check_digit;
+mod content;
+mod dedupe;
+mod encoding;
+mod feedback;
+mod framing;
+mod gs1;
+#[cfg(not(target_os = "espidf"))]
+pub mod mock;
+mod port;
+mod protocol;
+mod settings;
+mod stats;
+mod symbology;
+
+use dedupe::Deduplicator;
+use feedback::{Outputs, Player};
+use framing::{FrameEvent, Framer};
+use protocol::{zone, Command, ProtocolError, Response, ResponseParser};
+
+pub use baud::BaudRate;
+pub use check_digit::CheckDigitError;
+pub use content::{
+    redact_wifi_password, Contact, ContentError, GeoLocation, ScanContent, WebLink, WifiNetwork,
+    WifiSecurity,
+};
+pub use encoding::PayloadEncoding;
+pub use feedback::Feedback;
+pub use framing::Framing;
+pub use gs1::{Gs1Error, Gs1Message};
+pub use port::{ScannerPort, ScannerRx};
+pub use settings::{DataCut, Lighting, ScannerSettings, SymbologyFilter, Tail};
+pub use stats::ScanStats;
+pub use symbology::Symbology;
+
+/// Messages to the barcode scanner worker: control requests from handles,
+/// and data from the reader thread.
+#[derive(Debug, Clone)]
+enum ControlMessage {
+    /// Bytes received from the scanner.
+    Received(Vec<u8>),
+    /// The port failed to receive.
+    ReadError(String),
+    Trigger(bool), // true = active/scan, false = inactive
+    Led(bool),     // true = on, false = off
+    Beep(bool),    // true = on, false = off
+    /// Play a timed beep/LED pattern.
+    Feedback(Feedback),
+    /// Play feedback for each scan outcome.
+    ScanFeedback(bool),
+    /// Decode subsequent scans with this encoding.
+    Encoding(PayloadEncoding),
+    /// Split the byte stream into scans with this strategy.
+    Framing(Framing),
+    /// Largest scan to buffer, in bytes.
+    MaxScanLen(usize),
+    /// Drop repeat reads of the same label within this window.
+    DedupeWindow(Duration),
+    /// Whether Code 39 scans end in a mod 43 check character.
+    Code39CheckDigit(bool),
+    /// Move the scanner and the UART to a new baud rate.
+    BaudRate {
+        baud: BaudRate,
+        reply: Sender<Result<()>>,
+    },
+    /// Start a scan and report the next read, or time out.
+    ScanOnce {
+        timeout: Duration,
+        reply: Sender<Result<ScanResult, ScanError>>,
+    },
+    /// Send a serial command and report the reply payload.
+    Command {
+        command: Command,
+        reply: Sender<Result<Vec<u8>>>,
+    },
+}
+
+/// Output format flag enabling the Code ID prefix (zone bit 0x0060 bit 2).
+const OUTPUT_CODE_ID: u8 = 1 << 2;
+
+/// A decoded barcode read.
+#[derive(Debug, Clone)]
+pub struct ScanResult {
+    /// Symbology from the Code ID, if Code ID output is active and known.
+    pub symbology: Option<Symbology>,
+    /// Barcode text with the Code ID and tail removed.
+    pub payload: String,
+    /// Bytes as received from the scanner, excluding the tail.
+    pub raw_bytes: Vec<u8>,
+    /// When the read was received.
+    pub timestamp: SystemTime,
+}
+
+impl ScanResult {
+    /// Build a result from one received line; `None` if it is blank.
+    ///
+    /// Bytes that aren't valid in `encoding` are replaced with U+FFFD; see
+    /// [`is_binary`] for routing such frames to [`BinaryScan`] instead.
+    fn parse(raw: &[u8], code_id: bool, encoding: PayloadEncoding) -> Option<Self> {
+        let (text, _) = encoding.decode(raw);
+        let text = text.trim();
+        if text.is_empty() {
+            return None;
+        }
+
+        // Code IDs are a single ASCII letter in front of the data.
+        let (symbology, payload) = match text.as_bytes()[0] {
+            id if code_id && id.is_ascii_alphabetic() => {
+                let payload = &text[1..];
+                (Symbology::from_code_id(id, payload.as_bytes()), payload)
+            }
+            _ => (None, text),
+        };
+
+        Some(ScanResult {
+            symbology,
+            payload: payload.to_string(),
+            raw_bytes: raw.to_vec(),
+            timestamp: SystemTime::now(),
+        })
+    }
+
+    /// Parse GS1 Application Identifiers from the payload.
+    ///
+    /// Returns `None` for payloads that don't carry GS1 data: GS1 DataBar
+    /// always does, while Code128, DataMatrix and QR only count when marked
+    /// with FNC1, a GS1 symbology identifier or `(AI)` notation.
+    pub fn gs1(&self) -> Option<Result<Gs1Message, Gs1Error>> {
+        let is_gs1 = match self.symbology {
+            Some(Symbology::Rss14 | Symbology::RssLimited | Symbology::RssExpanded) => true,
+            Some(Symbology::Code128 | Symbology::DataMatrix | Symbology::Qr) | None => {
+                gs1::has_marker(&self.payload)
+            }
+            Some(_) => false,
+        };
+        is_gs1.then(|| gs1::parse(&self.payload, gs1::year_of(self.timestamp)))
+    }
+
+    /// Classify the payload as a URL, Wi-Fi credentials, contact card,
+    /// location or plain text.
+    pub fn content(&self) -> Result<ScanContent, ContentError> {
+        ScanContent::parse(&self.payload)
+    }
+
+    /// Validate the check digits of EAN/UPC, ITF-14 and GS1 element data,
+    /// and the Code 39 mod 43 check character if `code39_mod43` is set.
+    pub fn validate(&self, code39_mod43: bool) -> Result<(), CheckDigitError> {
+        match self.symbology {
+            Some(Symbology::Code39) if code39_mod43 => {
+                check_digit::validate_code39_mod43(&self.payload)?
+            }
+            Some(sym) => check_digit::validate(sym, &self.payload)?,
+            None => {}
+        }
+        if let Some(Ok(message)) = self.gs1() {
+            check_digit::validate_gs1(&message)?;
+        }
+        Ok(())
+    }
+}
+
+/// A read whose payload is not text, such as a binary or compressed QR code.
+#[derive(Debug, Clone)]
+pub struct BinaryScan {
+    /// Symbology from the Code ID, if Code ID output is active and known.
+    pub symbology: Option<Symbology>,
+    /// Payload bytes with the Code ID removed.
+    pub data: Vec<u8>,
+    /// When the read was received.
+    pub timestamp: SystemTime,
+}
+
+impl BinaryScan {
+    /// Build a result from one received frame; `None` if it is empty.
+    fn parse(raw: &[u8], code_id: bool) -> Option<Self> {
+        let (symbology, data) = match raw.first() {
+            Some(&id) if code_id && id.is_ascii_alphabetic() => {
+                (Symbology::from_code_id(id, &raw[1..]), &raw[1..])
+            }
+            _ => (None, raw),
+        };
+        if data.is_empty() {
+            return None;
+        }
+
+        Some(BinaryScan {
+            symbology,
+            data: data.to_vec(),
+            timestamp: SystemTime::now(),
+        })
+    }
+}
+
+/// Whether a frame should be reported as binary rather than text: it holds
+/// control bytes other than whitespace and the GS1/ISO 15434 separators
+/// (EOT, GS, RS), or isn't valid in the scanner's encoding.
+fn is_binary(raw: &[u8], encoding: PayloadEncoding) -> bool {
+    let has_control = raw.iter().any(|&b| {
+        (b < 0x20 && !matches!(b, b'\t' | b'\r' | b'\n' | 0x04 | 0x1D | 0x1E)) || b == 0x7F
+    });
+    has_control || encoding.decode(raw).1
+}
+
+/// Events produced by the barcode scanner worker.
+#[derive(Debug, Clone)]
+pub enum BarcodeEvent {
+    /// A successfully scanned barcode.
+    Scanned(ScanResult),
+
+    /// A successfully scanned barcode with a binary payload.
+    Binary(BinaryScan),
+
+    /// A scan that failed check digit validation.
+    Invalid {
+        scan: ScanResult,
+        reason: CheckDigitError,
+    },
+
+    /// The scanner answered at start, or again after being unreachable; in
+    /// that case the settings written since start have been re-applied.
+    Connected,
+
+    /// The scanner stopped answering the health probe.
+    Disconnected,
+
+    /// A non‑fatal error while reading or parsing.
+    Error(String),
+}
+
+impl BarcodeEvent {
+    /// Whether the event carries scanned data.
+    pub fn is_scan(&self) -> bool {
+        self.scan_key().is_some()
+    }
+
+    /// What a waiting [`BarcodeHandle::scan_once`] call gets for this event;
+    /// `None` if it isn't a read.
+    fn scan_result(&self) -> Option<Result<ScanResult, ScanError>> {
+        match self {
+            BarcodeEvent::Scanned(scan) => Some(Ok(scan.clone())),
+            BarcodeEvent::Invalid { reason, .. } => Some(Err(ScanError::Invalid(reason.clone()))),
+            BarcodeEvent::Binary(scan) => Some(Err(ScanError::Binary(scan.clone()))),
+            BarcodeEvent::Connected | BarcodeEvent::Disconnected | BarcodeEvent::Error(_) => None,
+        }
+    }
+
+    /// Pattern announcing this scan outcome, if any.
+    fn feedback(&self) -> Option<Feedback> {
+        match self {
+            BarcodeEvent::Scanned(_) | BarcodeEvent::Binary(_) => Some(Feedback::Success),
+            BarcodeEvent::Invalid { .. } => Some(Feedback::Error),
+            BarcodeEvent::Connected | BarcodeEvent::Disconnected | BarcodeEvent::Error(_) => None,
+        }
+    }
+
+    /// Symbology and payload identifying a scan, for duplicate suppression.
+    fn scan_key(&self) -> Option<(Option<Symbology>, &[u8])> {
+        match self {
+            BarcodeEvent::Scanned(scan) | BarcodeEvent::Invalid { scan, .. } => {
+                Some((scan.symbology, scan.payload.as_bytes()))
+            }
+            BarcodeEvent::Binary(scan) => Some((scan.symbology, &scan.data)),
+            BarcodeEvent::Connected | BarcodeEvent::Disconnected | BarcodeEvent::Error(_) => None,
+        }
+    }
+}
+
+/// Why [`BarcodeHandle::scan_once`] returned no scan.
+#[derive(Debug, Clone)]
+pub enum ScanError {
+    /// Nothing was read before the timeout.
+    Timeout,
+    /// Another one-shot scan is already waiting for a read.
+    Busy,
+    /// The scanner is not answering.
+    Disconnected,
+    /// The read failed check digit validation.
+    Invalid(CheckDigitError),
+    /// The read has a binary payload.
+    Binary(BinaryScan),
+    /// The scan could not be started.
+    Trigger(String),
+    /// The barcode worker has stopped.
+    WorkerStopped,
+}
+
+impl fmt::Display for ScanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScanError::Timeout => write!(f, "no barcode read before the timeout"),
+            ScanError::Busy => write!(f, "another scan is in progress"),
+            ScanError::Disconnected => write!(f, "scanner is disconnected"),
+            ScanError::Invalid(reason) => write!(f, "invalid check digit: {reason}"),
+            ScanError::Binary(scan) => write!(f, "binary payload of {} bytes", scan.data.len()),
+            ScanError::Trigger(e) => write!(f, "failed to start scan: {e}"),
+            ScanError::WorkerStopped => write!(f, "barcode worker has stopped"),
+        }
+    }
+}
+
+impl std::error::Error for ScanError {}
+
+/// A [`BarcodeHandle::scan_once`] call waiting for a read.
+struct PendingScan {
+    deadline: Instant,
+    reply: Sender<Result<ScanResult, ScanError>>,
+}
+
+/// Identification data reported by the scanner.
+#[derive(Debug, Clone, Copy)]
+pub struct ScannerInfo {
+    /// Product model; `0x05` is a GM65.
+    pub model: u8,
+    /// Hardware version, e.g. `0x64` = V1.00, `0x6E` = V1.10.
+    pub hardware_version: u8,
+    /// Software version, same encoding as the hardware version.
+    pub software_version: u8,
+}
+
+/// How on-demand scans are started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerMode {
+    /// Drive the TRIG GPIO.
+    Pin,
+    /// Send the serial trigger command; used when TRIG is not wired.
+    Command,
+}
+
+/// GM65 scan mode (zone bit 0x0000 bits 1-0).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanMode {
+    /// Scan while the trigger is held (module default).
+    Manual,
+    /// Scan when the serial trigger command is received.
+    Command,
+    /// Scan continuously.
+    Continuous,
+    /// Scan when the image sensor detects a change.
+    Sensor,
+}
+
+impl ScanMode {
+    const MASK: u8 = 0b0000_0011;
+
+    pub fn bits(self) -> u8 {
+        match self {
+            ScanMode::Manual => 0b00,
+            ScanMode::Command => 0b01,
+            ScanMode::Continuous => 0b10,
+            ScanMode::Sensor => 0b11,
+        }
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        match bits & Self::MASK {
+            0b00 => ScanMode::Manual,
+            0b01 => ScanMode::Command,
+            0b10 => ScanMode::Continuous,
+            _ => ScanMode::Sensor,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScanMode::Manual => "manual",
+            ScanMode::Command => "command",
+            ScanMode::Continuous => "continuous",
+            ScanMode::Sensor => "sensor",
+        }
+    }
+}
+
+impl FromStr for ScanMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "manual" => Ok(ScanMode::Manual),
+            "command" => Ok(ScanMode::Command),
+            "continuous" => Ok(ScanMode::Continuous),
+            "sensor" => Ok(ScanMode::Sensor),
+            _ => bail!("Unknown scan mode: {s}"),
+        }
+    }
+}
+
+/// Handle for interacting with the scanner subsystem.
+#[derive(Clone)]
+pub struct BarcodeHandle {
+    control_tx: Sender<ControlMessage>,
+    trigger_mode: TriggerMode,
+    connected: Arc<AtomicBool>,
+    stats: Arc<Mutex<ScanStats>>,
+}
+
+impl BarcodeHandle {
+    /// Whether the scanner answered the last health probe.
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// Scan and error counters since start or the last reset.
+    pub fn stats(&self) -> ScanStats {
+        self.stats.lock().unwrap().clone()
+    }
+
+    /// Zero the counters.
+    pub fn reset_stats(&self) {
+        *self.stats.lock().unwrap() = ScanStats::default();
+    }
+
+    /// Start or stop a scan.
+    ///
+    /// In [`TriggerMode::Pin`] this drives the trigger pin; on GM65 it is
+    /// typically active-low for manual trigger mode. In
+    /// [`TriggerMode::Command`] activating sends the serial trigger command
+    /// and fails if the scanner does not acknowledge it; deactivating is a
+    /// no-op because the scanner stops on output or read timeout.
+    pub fn set_trigger(&self, active: bool) -> Result<()> {
+        match self.trigger_mode {
+            TriggerMode::Pin => {
+                self.control_tx.send(ControlMessage::Trigger(active))?;
+            }
+            TriggerMode::Command => {
+                if active {
+                    self.trigger_scan()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Start a scan, wait for the next read and release the trigger.
+    ///
+    /// The read is also reported to `on_event` as usual. Blocks for up to
+    /// `timeout`, so don't call this from the `on_event` callback.
+    pub fn scan_once(&self, timeout: Duration) -> Result<ScanResult, ScanError> {
+        let (reply_tx, reply_rx) = mpsc::channel();
+        self.control_tx
+            .send(ControlMessage::ScanOnce {
+                timeout,
+                reply: reply_tx,
+            })
+            .map_err(|_| ScanError::WorkerStopped)?;
+        reply_rx.recv().map_err(|_| ScanError::WorkerStopped)?
+    }
+
+    pub fn trigger_mode(&self) -> TriggerMode {
+        self.trigger_mode
+    }
+
+    /// Start a scan with the serial trigger command and wait for the ack.
+    ///
+    /// Equivalent to the datasheet's `7E 00 08 01 00 02 01 AB CD`, sent with a
+    /// real CRC. The scanner must be in command-triggered scan mode.
+    pub fn trigger_scan(&self) -> Result<()> {
+        self.write_zone(zone::COMMAND_TRIGGER, &[0x01])
+            .context("Scanner did not acknowledge trigger command")
+    }
+
+    /// Control the LED pin (on/off).
+    pub fn set_led(&self, on: bool) -> Result<()> {
+        self.control_tx.send(ControlMessage::Led(on))?;
+        Ok(())
+    }
+
+    /// Control the beep pin (on/off).
+    pub fn set_beep(&self, on: bool) -> Result<()> {
+        self.control_tx.send(ControlMessage::Beep(on))?;
+        Ok(())
+    }
+
+    /// Read `len` consecutive zone bytes starting at `address` (1..=256).
+    ///
+    /// Blocks until the scanner replies, so it must not be called from the
+    /// `on_event` callback, which runs on the worker thread.
+    pub fn read_zone(&self, address: u16, len: usize) -> Result<Vec<u8>> {
+        self.command(Command::ReadZone { address, len })
+    }
+
+    /// Write `data` to consecutive zone bytes starting at `address` (1..=245 bytes).
+    ///
+    /// Changes are volatile until saved to the scanner's flash.
+    /// Same blocking caveat as [`BarcodeHandle::read_zone`].
+    pub fn write_zone(&self, address: u16, data: &[u8]) -> Result<()> {
+        self.command(Command::WriteZone {
+            address,
+            data: data.to_vec(),
+        })?;
+        Ok(())
+    }
+
+    /// Read the current scan mode from the scanner.
+    pub fn scan_mode(&self) -> Result<ScanMode> {
+        let settings = self.read_zone_byte(zone::SETTINGS)?;
+        Ok(ScanMode::from_bits(settings))
+    }
+
+    /// Switch the scanner's scan mode, leaving the other settings bits intact.
+    pub fn set_scan_mode(&self, mode: ScanMode) -> Result<()> {
+        self.update_zone_bits(zone::SETTINGS, ScanMode::MASK, mode.bits())
+    }
+
+    /// Read the aiming light mode (zone bit 0x0000 bits 5-4).
+    pub fn aim(&self) -> Result<Lighting> {
+        let settings = self.read_zone_byte(zone::SETTINGS)?;
+        Ok(Lighting::from_bits(settings >> Lighting::AIM_SHIFT))
+    }
+
+    /// Switch the aiming light mode, leaving the other settings bits intact.
+    pub fn set_aim(&self, mode: Lighting) -> Result<()> {
+        self.update_zone_bits(
+            zone::SETTINGS,
+            Lighting::MASK << Lighting::AIM_SHIFT,
+            mode.bits() << Lighting::AIM_SHIFT,
+        )
+    }
+
+    /// Read the illumination mode (zone bit 0x0000 bits 3-2).
+    pub fn illumination(&self) -> Result<Lighting> {
+        let settings = self.read_zone_byte(zone::SETTINGS)?;
+        Ok(Lighting::from_bits(
+            settings >> Lighting::ILLUMINATION_SHIFT,
+        ))
+    }
+
+    /// Switch the illumination mode, leaving the other settings bits intact.
+    pub fn set_illumination(&self, mode: Lighting) -> Result<()> {
+        self.update_zone_bits(
+            zone::SETTINGS,
+            Lighting::MASK << Lighting::ILLUMINATION_SHIFT,
+            mode.bits() << Lighting::ILLUMINATION_SHIFT,
+        )
+    }
+
+    /// Read how long the scanner tries to decode a single read.
+    /// Zero means it keeps trying.
+    pub fn read_timeout(&self) -> Result<Duration> {
+        Ok(settings::time_from_units(
+            self.read_zone_byte(zone::READ_TIMEOUT)?,
+        ))
+    }
+
+    /// Set how long the scanner tries to decode a single read: 0.1 s to
+    /// 25.5 s in 0.1 s steps, or zero for no limit.
+    pub fn set_read_timeout(&self, timeout: Duration) -> Result<()> {
+        let units = settings::time_units(timeout, "read timeout")?;
+        self.write_zone(zone::READ_TIMEOUT, &[units])
+    }
+
+    /// Read the pause before the scanner reads the same code again.
+    pub fn read_interval(&self) -> Result<Duration> {
+        Ok(settings::time_from_units(
+            self.read_zone_byte(zone::READ_INTERVAL)?,
+        ))
+    }
+
+    /// Set the pause before the scanner reads the same code again: 0.1 s
+    /// to 25.5 s in 0.1 s steps, or zero for no repeat.
+    pub fn set_read_interval(&self, interval: Duration) -> Result<()> {
+        let units = settings::time_units(interval, "read interval")?;
+        self.write_zone(zone::READ_INTERVAL, &[units])
+    }
+
+    /// Allow or forbid reading a symbology.
+    pub fn set_symbology_enabled(&self, symbology: Symbology, enabled: bool) -> Result<()> {
+        self.update_zone_bits(symbology.zone(), 0x01, enabled as u8)
+    }
+
+    /// Read which symbologies the scanner currently accepts.
+    pub fn enabled_symbologies(&self) -> Result<Vec<Symbology>> {
+        let len = (Symbology::ZONE_LAST - Symbology::ZONE_FIRST + 1) as usize;
+        let zones = self.read_zone(Symbology::ZONE_FIRST, len)?;
+
+        Ok(Symbology::ALL
+            .into_iter()
+            .filter(|sym| {
+                zones
+                    .get((sym.zone() - Symbology::ZONE_FIRST) as usize)
+                    .is_some_and(|flags| flags & 0x01 != 0)
+            })
+            .collect())
+    }
+
+    /// Switch the scanner's output encoding and decode scans accordingly.
+    pub fn set_encoding(&self, encoding: PayloadEncoding) -> Result<()> {
+        self.update_zone_bits(zone::ENCODING, PayloadEncoding::MASK, encoding.bits())?;
+        self.control_tx.send(ControlMessage::Encoding(encoding))?;
+        Ok(())
+    }
+
+    /// Change how the byte stream is split into scans.
+    /// Any partially received scan is discarded.
+    pub fn set_framing(&self, framing: Framing) -> Result<()> {
+        framing.validate()?;
+        self.control_tx.send(ControlMessage::Framing(framing))?;
+        Ok(())
+    }
+
+    /// Set the largest scan the worker buffers, in bytes; longer scans are
+    /// reported as "Barcode too long". Any partially received scan is
+    /// discarded.
+    pub fn set_max_scan_len(&self, max_len: usize) -> Result<()> {
+        self.control_tx.send(ControlMessage::MaxScanLen(max_len))?;
+        Ok(())
+    }
+
+    /// Drop reads that repeat the previous label (same payload and symbology)
+    /// within `window` of its last read; zero reports every read.
+    pub fn set_dedupe_window(&self, window: Duration) -> Result<()> {
+        self.control_tx.send(ControlMessage::DedupeWindow(window))?;
+        Ok(())
+    }
+
+    /// Treat the last character of Code 39 scans as a mod 43 check
+    /// character and reject scans where it doesn't match.
+    pub fn set_code39_check_digit(&self, enabled: bool) -> Result<()> {
+        self.control_tx
+            .send(ControlMessage::Code39CheckDigit(enabled))?;
+        Ok(())
+    }
+
+    /// Play a beep/LED pattern on the feedback pins. Returns at once; a
+    /// later pattern or `set_led`/`set_beep` call cuts it short.
+    pub fn play_feedback(&self, feedback: Feedback) -> Result<()> {
+        self.control_tx.send(ControlMessage::Feedback(feedback))?;
+        Ok(())
+    }
+
+    /// Play [`Feedback::Success`] for good reads, [`Feedback::Error`] for
+    /// check digit failures and [`Feedback::Warning`] for oversized scans.
+    pub fn set_scan_feedback(&self, enabled: bool) -> Result<()> {
+        self.control_tx
+            .send(ControlMessage::ScanFeedback(enabled))?;
+        Ok(())
+    }
+
+    /// Baud rate the scanner is currently configured for.
+    pub fn baud_rate(&self) -> Result<BaudRate> {
+        match self.read_zone(zone::BAUD_RATE, 2)?[..] {
+            [low, high] => BaudRate::from_zone_bytes([low, high])
+                .with_context(|| format!("Unknown baud rate divider {low:02X} {high:02X}")),
+            ref other => bail!("Unexpected baud rate reply: {other:02X?}"),
+        }
+    }
+
+    /// Switch the scanner and the UART to `baud`. The setting is not saved
+    /// to the scanner's flash, so it falls back to its stored rate after a
+    /// power cycle; the worker finds it again on start.
+    pub fn set_baud_rate(&self, baud: BaudRate) -> Result<()> {
+        let (reply_tx, reply_rx) = mpsc::channel();
+        self.control_tx.send(ControlMessage::BaudRate {
+            baud,
+            reply: reply_tx,
+        })?;
+        reply_rx.recv()?
+    }
+
+    /// Read the scanner's zone bits into a [`ScannerSettings`].
+    pub fn read_settings(&self) -> Result<ScannerSettings> {
+        let zones = self.read_zone(zone::SETTINGS, settings::ZONE_LEN)?;
+        ScannerSettings::from_zones(&zones)
+    }
+
+    /// Configure the scanner from `settings`, writing only the zone bytes
+    /// that change. A new baud rate is switched to last, with the UART
+    /// following. Like other writes this is volatile until
+    /// [`BarcodeHandle::save_to_flash`].
+    pub fn apply_settings(&self, settings: &ScannerSettings) -> Result<()> {
+        let current = self.read_zone(zone::SETTINGS, settings::ZONE_LEN)?;
+        let mut updated = current.clone();
+        settings.write_zones(&mut updated)?;
+        for (address, data) in settings::changed_runs(&current, &updated) {
+            self.write_zone(address, &data)?;
+        }
+
+        // Let the worker decode with the new encoding.
+        self.set_encoding(settings.encoding)?;
+        if self.baud_rate()? != settings.baud {
+            self.set_baud_rate(settings.baud)?;
+        }
+        Ok(())
+    }
+
+    /// Save the current zone bits to the scanner's internal flash, so they
+    /// are kept across power cycles.
+    pub fn save_to_flash(&self) -> Result<()> {
+        self.command(Command::SaveToFlash)?;
+        Ok(())
+    }
+
+    /// Read the model and firmware versions from the scanner.
+    pub fn info(&self) -> Result<ScannerInfo> {
+        match self.read_zone(zone::PRODUCT_INFO, 3)?[..] {
+            [model, hardware_version, software_version] => Ok(ScannerInfo {
+                model,
+                hardware_version,
+                software_version,
+            }),
+            ref other => bail!("Unexpected product info reply: {other:02X?}"),
+        }
+    }
+
+    fn read_zone_byte(&self, address: u16) -> Result<u8> {
+        self.read_zone(address, 1)?
+            .first()
+            .copied()
+            .with_context(|| format!("Empty reply reading zone {address:#06X}"))
+    }
+
+    /// Read-modify-write the bits selected by `mask` in a single zone byte.
+    fn update_zone_bits(&self, address: u16, mask: u8, value: u8) -> Result<()> {
+        let current = self.read_zone_byte(address)?;
+        let updated = (current & !mask) | (value & mask);
+        if updated != current {
+            self.write_zone(address, &[updated])?;
+        }
+        Ok(())
+    }
+
+    fn command(&self, command: Command) -> Result<Vec<u8>> {
+        let (reply_tx, reply_rx) = mpsc::channel();
+        self.control_tx.send(ControlMessage::Command {
+            command,
+            reply: reply_tx,
+        })?;
+        reply_rx.recv()?
+    }
+}
+
+/// Owns the scanner worker thread and the port to the scanner.
+pub struct BarcodeScanner {
+    handle: BarcodeHandle,
+}
+
+impl BarcodeScanner {
+    /// Start the scanner worker.
+    ///
+    /// `port` must be configured for the GM65 default: 9600 8N1, no flow control;
+    /// on the device it is the firmware's UART port, in tests a `mock::MockScanner`.
+    /// `trigger`, `led`, `beep` are optional GPIO control pins for the GM65.
+    /// Without `trigger`, scans are started with the serial trigger command.
+    /// `on_event` is invoked from the worker thread whenever a barcode or error occurs.
+    pub fn start<F, P, TRIG, LED, BEEP>(
+        mut port: P,
+        trigger: Option<TRIG>,
+        led: Option<LED>,
+        beep: Option<BEEP>,
+        mut on_event: F,
+    ) -> Result<Self>
+    where
+        F: 'static + Send + FnMut(BarcodeEvent),
+        P: 'static + Send + ScannerPort,
+        TRIG: 'static + Send + OutputPin,
+        LED: 'static + Send + OutputPin,
+        BEEP: 'static + Send + OutputPin,
+    {
+        let (control_tx, control_rx) = mpsc::channel::<ControlMessage>();
+        let trigger_mode = if trigger.is_some() {
+            TriggerMode::Pin
+        } else {
+            TriggerMode::Command
+        };
+        let connected = Arc::new(AtomicBool::new(false));
+        let worker_connected = connected.clone();
+        let stats = Arc::new(Mutex::new(ScanStats::default()));
+        let worker_stats = stats.clone();
+
+        let mut rx = port.take_rx()?;
+        let reader_tx = control_tx.clone();
+        thread::Builder::new()
+            .name("barcode-reader".into())
+            .stack_size(4096)
+            .spawn(move || run_reader(&mut rx, &reader_tx))
+            .context("Failed to spawn barcode reader thread")?;
+
+        // Use a modest stack; this worker does simple I/O + small buffers.
+        thread::Builder::new()
+            .name("barcode-worker".into())
+            .stack_size(4096)
+            .spawn(move || {
+                if let Err(e) = run_worker(
+                    Link::new(port, control_rx),
+                    trigger,
+                    led,
+                    beep,
+                    &worker_connected,
+                    &worker_stats,
+                    &mut on_event,
+                ) {
+                    // Avoid heavy logging if that causes issues; if you see problems,
+                    // you can remove this log and surface via another mechanism.
+                    log::error!("Barcode worker exited with error: {e:?}");
+                }
+            })
+            .context("Failed to spawn barcode worker thread")?;
+
+        Ok(Self {
+            handle: BarcodeHandle {
+                control_tx,
+                trigger_mode,
+                connected,
+                stats,
+            },
+        })
+    }
+
+    pub fn handle(&self) -> BarcodeHandle {
+        self.handle.clone()
+    }
+}
+
+const DEFAULT_MAX_SCAN_LEN: usize = 128;
+const HOUSEKEEPING_INTERVAL: Duration = Duration::from_millis(200); // worker wakeup without messages
+const FRAME_POLL: Duration = Duration::from_millis(10); // wakeup while a scan is partially received
+const READER_WAKE: Duration = Duration::from_secs(1); // reader wait for data before re-checking
+const READ_BUFFER_LEN: usize = 1024;
+const COMMAND_TIMEOUT: Duration = Duration::from_millis(500); // GM65 drops commands not completed within 400 ms
+const HEALTH_PROBE_INTERVAL: Duration = Duration::from_secs(5); // after the last byte received
+const HEALTH_PROBE_FAILURES: u32 = 2; // missed probes before reporting a disconnect
+const FULL_BAUD_SEARCH_PROBES: u32 = 12; // reconnect probes per search of every rate
+
+/// Settings written through the worker since start, re-applied when the
+/// scanner reconnects after a power loss.
+#[derive(Default)]
+struct AppliedSettings {
+    baud: Option<BaudRate>,
+    /// Zone writes in order, latest per address.
+    writes: Vec<(u16, Vec<u8>)>,
+}
+
+impl AppliedSettings {
+    fn record(&mut self, command: &Command) {
+        if let Command::WriteZone { address, data } = command {
+            // Triggers are one-shot and the baud rate is tracked separately.
+            if *address == zone::COMMAND_TRIGGER || *address == zone::BAUD_RATE {
+                return;
+            }
+            self.writes.retain(|(written, _)| written != address);
+            self.writes.push((*address, data.clone()));
+        }
+    }
+}
+
+/// Forward received bytes to the worker as they arrive, a whole scan at a
+/// time where the port can tell. Stops at the first message after the
+/// worker has exited.
+fn run_reader<R: ScannerRx>(rx: &mut R, worker: &Sender<ControlMessage>) {
+    let mut buf = vec![0u8; READ_BUFFER_LEN];
+    loop {
+        let message = match rx.read_burst(&mut buf, READER_WAKE) {
+            Ok(0) => continue,
+            Ok(len) => ControlMessage::Received(buf[..len].to_vec()),
+            Err(e) => {
+                // Don't spin on a port that keeps failing.
+                thread::sleep(HOUSEKEEPING_INTERVAL);
+                ControlMessage::ReadError(format!("{e:?}"))
+            }
+        };
+        if worker.send(message).is_err() {
+            return;
+        }
+    }
+}
+
+fn run_worker<F, P, TRIG, LED, BEEP>(
+    mut link: Link<P>,
+    mut trigger: Option<TRIG>,
+    mut led: Option<LED>,
+    mut beep: Option<BEEP>,
+    connected: &AtomicBool,
+    stats: &Mutex<ScanStats>,
+    on_event: &mut F,
+) -> Result<()>
+where
+    F: Send + FnMut(BarcodeEvent),
+    P: ScannerPort,
+    TRIG: 'static + Send + OutputPin,
+    LED: 'static + Send + OutputPin,
+    BEEP: 'static + Send + OutputPin,
+{
+    let mut framer = Framer::new(Framing::default(), DEFAULT_MAX_SCAN_LEN);
+    let mut dedupe = Deduplicator::new(Duration::ZERO);
+    let mut code39_check = false;
+    let mut player = Player::new();
+    let mut scan_feedback = false;
+
+    let mut settings = AppliedSettings::default();
+    let mut pending_scan: Option<PendingScan> = None;
+    let mut probe_failures = 0;
+    let mut reconnect_probes = 0;
+    let mut next_probe = Instant::now() + HEALTH_PROBE_INTERVAL;
+    // Where the scanner comes back after losing power.
+    let mut power_up_baud = BaudRate::PROBE_ORDER[0];
+
+    // The scanner may have been moved off its default rate by another tool;
+    // find the rate it answers on before sending anything else.
+    match detect_baud_rate(&mut link) {
+        Ok(baud) => {
+            log::info!("GM65 answers at {baud}");
+            power_up_baud = baud;
+            connected.store(true, Ordering::Relaxed);
+            on_event(BarcodeEvent::Connected);
+        }
+        Err(e) => {
+            on_event(BarcodeEvent::Error(format!(
+                "Failed to detect scanner baud rate: {e:?}"
+            )));
+            on_event(BarcodeEvent::Disconnected);
+        }
+    }
+    let (mut code_id, mut encoding) = configure_scanner(&mut link, &settings, on_event);
+    set_burst_terminator(&mut link, &Framing::default());
+
+    loop {
+        // Wake quickly while a scan is partially received so idle-gap
+        // framing ends it promptly, and while a feedback pattern plays.
+        let timeout = if framer.is_pending() || player.is_playing() {
+            FRAME_POLL
+        } else {
+            HOUSEKEEPING_INTERVAL
+        };
+
+        let mut frames = Vec::new();
+        if let Some(msg) = link.next_message(timeout) {
+            match msg {
+                ControlMessage::Received(bytes) => {
+                    if connected.load(Ordering::Relaxed) {
+                        next_probe = Instant::now() + HEALTH_PROBE_INTERVAL;
+                    }
+                    let now = Instant::now();
+                    frames.extend(bytes.into_iter().filter_map(|byte| framer.push(byte, now)));
+                }
+                ControlMessage::ReadError(e) => {
+                    stats.lock().unwrap().read_errors += 1;
+                    on_event(BarcodeEvent::Error(format!("Scanner read error: {e}")));
+                }
+                ControlMessage::Trigger(active) => {
+                    set_trigger_pin(&mut trigger, active);
+                }
+                ControlMessage::Led(on) => {
+                    player.stop();
+                    set_output_pin(&mut led, on);
+                }
+                ControlMessage::Beep(on) => {
+                    player.stop();
+                    set_output_pin(&mut beep, on);
+                }
+                ControlMessage::Feedback(feedback) => {
+                    let outputs = player.play(feedback, Instant::now());
+                    set_outputs(&mut beep, &mut led, outputs);
+                }
+                ControlMessage::ScanFeedback(enabled) => {
+                    scan_feedback = enabled;
+                }
+                ControlMessage::Encoding(new_encoding) => {
+                    encoding = new_encoding;
+                }
+                ControlMessage::Framing(framing) => {
+                    set_burst_terminator(&mut link, &framing);
+                    framer.set_framing(framing);
+                }
+                ControlMessage::MaxScanLen(max_len) => {
+                    framer.set_max_len(max_len);
+                }
+                ControlMessage::DedupeWindow(window) => {
+                    dedupe.set_window(window);
+                }
+                ControlMessage::Code39CheckDigit(enabled) => {
+                    code39_check = enabled;
+                }
+                ControlMessage::BaudRate { baud, reply } => {
+                    let result = switch_baud_rate(&mut link, baud);
+                    if result.is_ok() {
+                        settings.baud = Some(baud);
+                    }
+                    framer.reset();
+                    let _ = reply.send(result);
+                }
+                ControlMessage::ScanOnce { timeout, reply } => {
+                    if pending_scan.is_some() {
+                        reply.send(Err(ScanError::Busy)).ok();
+                    } else if !connected.load(Ordering::Relaxed) {
+                        reply.send(Err(ScanError::Disconnected)).ok();
+                    } else {
+                        match start_scan(&mut link, &mut trigger) {
+                            Ok(()) => {
+                                pending_scan = Some(PendingScan {
+                                    deadline: Instant::now() + timeout,
+                                    reply,
+                                });
+                            }
+                            Err(e) => {
+                                reply.send(Err(ScanError::Trigger(format!("{e:?}")))).ok();
+                            }
+                        }
+                    }
+                }
+                ControlMessage::Command { command, reply } => {
+                    let result = link.execute(&command);
+                    match result {
+                        Ok(_) => settings.record(&command),
+                        Err(ref e) => log::warn!("GM65 command {command:?} failed: {e:?}"),
+                    }
+                    // The caller may have given up waiting; that's fine.
+                    reply.send(result).ok();
+                }
+            }
+        }
+
+        if let Some(pending) = pending_scan.take() {
+            if Instant::now() >= pending.deadline {
+                set_trigger_pin(&mut trigger, false);
+                pending.reply.send(Err(ScanError::Timeout)).ok();
+            } else {
+                pending_scan = Some(pending);
+            }
+        }
+
+        // Probe the scanner once the line has been quiet for a while; any
+        // received byte already shows it is alive. Don't talk over a scan
+        // that is being received or awaited.
+        if Instant::now() >= next_probe && !framer.is_pending() && pending_scan.is_none() {
+            next_probe = Instant::now() + HEALTH_PROBE_INTERVAL;
+
+            if connected.load(Ordering::Relaxed) {
+                if read_baud_rate(&mut link).is_ok() {
+                    probe_failures = 0;
+                } else {
+                    probe_failures += 1;
+                    if probe_failures == HEALTH_PROBE_FAILURES {
+                        connected.store(false, Ordering::Relaxed);
+                        reconnect_probes = 0;
+                        on_event(BarcodeEvent::Disconnected);
+                    }
+                }
+            } else {
+                // A scanner that lost power is back at its power-up rate, one
+                // that was only unplugged is still at the rate set since. Try
+                // one rate per probe so commands keep being served, and
+                // search every rate only now and then.
+                reconnect_probes += 1;
+                let found = if reconnect_probes % FULL_BAUD_SEARCH_PROBES == 0 {
+                    detect_baud_rate(&mut link).is_ok()
+                } else {
+                    let baud = match settings.baud {
+                        Some(baud) if reconnect_probes % 2 == 0 => baud,
+                        _ => power_up_baud,
+                    };
+                    probe_baud_rate(&mut link, baud).is_ok()
+                };
+                if found {
+                    // It is back at its stored settings.
+                    probe_failures = 0;
+                    (code_id, encoding) = configure_scanner(&mut link, &settings, on_event);
+                    connected.store(true, Ordering::Relaxed);
+                    on_event(BarcodeEvent::Connected);
+                }
+            }
+        }
+
+        if let Some(outputs) = player.poll(Instant::now()) {
+            set_outputs(&mut beep, &mut led, outputs);
+        }
+
+        // Without new bytes, a pending scan may now be complete.
+        frames.extend(framer.poll(Instant::now()));
+
+        for frame_event in frames {
+            match frame_event {
+                FrameEvent::Frame(frame) => {
+                    let event = if is_binary(&frame, encoding) {
+                        BinaryScan::parse(&frame, code_id).map(BarcodeEvent::Binary)
+                    } else {
+                        ScanResult::parse(&frame, code_id, encoding).map(|scan| {
+                            match scan.validate(code39_check) {
+                                Ok(()) => BarcodeEvent::Scanned(scan),
+                                Err(reason) => BarcodeEvent::Invalid { scan, reason },
+                            }
+                        })
+                    };
+                    if let Some(event) = event {
+                        // A waiting one-shot scan takes the read even if it
+                        // repeats the previous one.
+                        if let Some(pending) = pending_scan.take() {
+                            match event.scan_result() {
+                                Some(result) => {
+                                    set_trigger_pin(&mut trigger, false);
+                                    pending.reply.send(result).ok();
+                                }
+                                None => pending_scan = Some(pending),
+                            }
+                        }
+
+                        let is_new = match event.scan_key() {
+                            Some((symbology, payload)) => {
+                                dedupe.check(symbology, payload, Instant::now())
+                            }
+                            None => true,
+                        };
+                        if is_new {
+                            if let Some((symbology, _)) = event.scan_key() {
+                                let mut stats = stats.lock().unwrap();
+                                stats.record_scan(symbology, Instant::now());
+                                if matches!(event, BarcodeEvent::Invalid { .. }) {
+                                    stats.invalid += 1;
+                                }
+                            }
+                            if let Some(feedback) = event.feedback().filter(|_| scan_feedback) {
+                                let outputs = player.play(feedback, Instant::now());
+                                set_outputs(&mut beep, &mut led, outputs);
+                            }
+                            on_event(event);
+                        } else {
+                            stats.lock().unwrap().dedupe_drops += 1;
+                            log::debug!("Dropped repeat scan ({} so far)", dedupe.repeats());
+                        }
+                    }
+                }
+                FrameEvent::Overflow => {
+                    stats.lock().unwrap().overflows += 1;
+                    if scan_feedback {
+                        let outputs = player.play(Feedback::Warning, Instant::now());
+                        set_outputs(&mut beep, &mut led, outputs);
+                    }
+                    on_event(BarcodeEvent::Error("Barcode too long".into()));
+                }
+            }
+        }
+    }
+}
+
+/// Have the port end receive bursts where the framing ends a scan.
+fn set_burst_terminator<P: ScannerPort>(link: &mut Link<P>, framing: &Framing) {
+    let terminator = match framing {
+        // CR also ends CRLF tails; the LF arrives with the next burst.
+        Framing::Terminator(bytes) => bytes.first().copied(),
+        Framing::Envelope { suffix, .. } => suffix.last().copied(),
+        Framing::IdleGap(_) | Framing::FixedLength(_) => None,
+    };
+    if let Err(e) = link.port.set_terminator(terminator) {
+        log::warn!("Failed to set receive terminator: {e:?}");
+    }
+}
+
+/// Drive the trigger pin, if wired; the GM65 trigger is active-low.
+fn set_trigger_pin<TRIG: OutputPin>(trigger: &mut Option<TRIG>, active: bool) {
+    if let Some(pin) = trigger {
+        if active {
+            pin.set_low().ok();
+        } else {
+            pin.set_high().ok();
+        }
+    }
+}
+
+/// Drive an active-high output pin, if wired.
+fn set_output_pin<X: OutputPin>(pin: &mut Option<X>, on: bool) {
+    if let Some(pin) = pin {
+        if on {
+            pin.set_high().ok();
+        } else {
+            pin.set_low().ok();
+        }
+    }
+}
+
+/// Apply a feedback pattern step to the beep and LED pins.
+fn set_outputs<BEEP: OutputPin, LED: OutputPin>(
+    beep: &mut Option<BEEP>,
+    led: &mut Option<LED>,
+    outputs: Outputs,
+) {
+    set_output_pin(beep, outputs.beep);
+    set_output_pin(led, outputs.led);
+}
+
+/// Start a scan with the trigger pin, or the serial trigger command if the
+/// pin isn't wired.
+fn start_scan<P: ScannerPort, TRIG: OutputPin>(
+    link: &mut Link<P>,
+    trigger: &mut Option<TRIG>,
+) -> Result<()> {
+    if trigger.is_some() {
+        set_trigger_pin(trigger, true);
+        return Ok(());
+    }
+    let write = Command::WriteZone {
+        address: zone::COMMAND_TRIGGER,
+        data: vec![0x01],
+    };
+    link.execute(&write)?;
+    Ok(())
+}
+
+/// Put a newly found scanner in the state the worker expects: restore the
+/// baud rate and settings written since start, turn on Code ID output and
+/// read the payload encoding. Returns whether Code ID output is on and the
+/// encoding to decode with.
+fn configure_scanner<F, P: ScannerPort>(
+    link: &mut Link<P>,
+    settings: &AppliedSettings,
+    on_event: &mut F,
+) -> (bool, PayloadEncoding)
+where
+    F: FnMut(BarcodeEvent),
+{
+    if let Some(baud) = settings.baud {
+        if let Err(e) = switch_baud_rate(link, baud) {
+            on_event(BarcodeEvent::Error(format!(
+                "Failed to restore baud rate: {e:?}"
+            )));
+        }
+    }
+    for (address, data) in &settings.writes {
+        let write = Command::WriteZone {
+            address: *address,
+            data: data.clone(),
+        };
+        if let Err(e) = link.execute(&write) {
+            on_event(BarcodeEvent::Error(format!(
+                "Failed to restore zone {address:#06X}: {e:?}"
+            )));
+        }
+    }
+
+    // Ask for Code ID output so scans carry their symbology. If the scanner
+    // doesn't answer, treat scans as plain text rather than guessing.
+    let code_id = match enable_code_id(link) {
+        Ok(()) => true,
+        Err(e) => {
+            on_event(BarcodeEvent::Error(format!(
+                "Failed to enable Code ID output: {e:?}"
+            )));
+            false
+        }
+    };
+
+    // Decode with whatever encoding the scanner is configured for.
+    let encoding = match read_encoding(link) {
+        Ok(encoding) => encoding,
+        Err(e) => {
+            on_event(BarcodeEvent::Error(format!(
+                "Failed to read payload encoding: {e:?}"
+            )));
+            PayloadEncoding::default()
+        }
+    };
+
+    (code_id, encoding)
+}
+
+/// Turn on Code ID output, leaving the other output format flags intact.
+fn enable_code_id<P: ScannerPort>(link: &mut Link<P>) -> Result<()> {
+    let read = Command::ReadZone {
+        address: zone::OUTPUT_FORMAT,
+        len: 1,
+    };
+    let flags = *link
+        .execute(&read)?
+        .first()
+        .context("Empty reply reading output format")?;
+
+    if flags & OUTPUT_CODE_ID == 0 {
+        let write = Command::WriteZone {
+            address: zone::OUTPUT_FORMAT,
+            data: vec![flags | OUTPUT_CODE_ID],
+        };
+        link.execute(&write)?;
+    }
+    Ok(())
+}
+
+/// Read the payload encoding the scanner is configured for.
+fn read_encoding<P: ScannerPort>(link: &mut Link<P>) -> Result<PayloadEncoding> {
+    let read = Command::ReadZone {
+        address: zone::ENCODING,
+        len: 1,
+    };
+    let flags = *link
+        .execute(&read)?
+        .first()
+        .context("Empty reply reading payload encoding")?;
+    Ok(PayloadEncoding::from_bits(flags))
+}
+
+/// Probe the supported baud rates with a zone-bit query, starting with the
+/// port's current rate, and leave the port on the first one that answers.
+fn detect_baud_rate<P: ScannerPort>(link: &mut Link<P>) -> Result<BaudRate> {
+    let current = BaudRate::from_bps(link.port.baud_rate()?);
+    let candidates = current.into_iter().chain(
+        BaudRate::PROBE_ORDER
+            .into_iter()
+            .filter(|&baud| Some(baud) != current),
+    );
+
+    for baud in candidates {
+        link.port.set_baud_rate(baud.bps())?;
+        link.clear_rx()?;
+        if read_baud_rate(link).is_ok() {
+            return Ok(baud);
+        }
+    }
+
+    if let Some(current) = current {
+        link.port.set_baud_rate(current.bps())?;
+    }
+    // Drop what was received at the wrong rates rather than framing it.
+    link.clear_rx()?;
+    bail!("GM65 did not answer at any supported baud rate")
+}
+
+/// Move the port to `baud` and check the scanner answers there.
+fn probe_baud_rate<P: ScannerPort>(link: &mut Link<P>, baud: BaudRate) -> Result<()> {
+    link.port.set_baud_rate(baud.bps())?;
+    link.clear_rx()?;
+    read_baud_rate(link)?;
+    Ok(())
+}
+
+/// Move the scanner to `baud`, then the port, and check it still answers.
+fn switch_baud_rate<P: ScannerPort>(link: &mut Link<P>, baud: BaudRate) -> Result<()> {
+    let write = Command::WriteZone {
+        address: zone::BAUD_RATE,
+        data: baud.zone_bytes().to_vec(),
+    };
+    // The reply may be sent at either rate, so rely on the read-back below.
+    if let Err(e) = link.execute(&write) {
+        log::debug!("No reply to baud rate change: {e:?}");
+    }
+
+    link.port.set_baud_rate(baud.bps())?;
+    link.clear_rx()?;
+    if read_baud_rate(link).is_ok() {
+        return Ok(());
+    }
+
+    // Get back in sync with wherever the scanner ended up.
+    let actual = detect_baud_rate(link)?;
+    bail!("GM65 did not switch to {baud}; still at {actual}")
+}
+
+/// Read the baud rate the scanner is configured for.
+fn read_baud_rate<P: ScannerPort>(link: &mut Link<P>) -> Result<BaudRate> {
+    let read = Command::ReadZone {
+        address: zone::BAUD_RATE,
+        len: 2,
+    };
+    match link.execute(&read)?[..] {
+        [low, high] => BaudRate::from_zone_bytes([low, high]).context("Unknown baud rate divider"),
+        ref other => bail!("Unexpected baud rate reply: {other:02X?}"),
+    }
+}
+
+/// The worker's end of the scanner connection: the port for writing, and
+/// the channel the reader thread forwards received bytes on. Control
+/// messages that arrive while a command waits for its reply are held back
+/// for the main loop, and so are scan bytes received around the reply.
+struct Link<P> {
+    port: P,
+    messages: Receiver<ControlMessage>,
+    deferred: VecDeque<ControlMessage>,
+    parser: ResponseParser,
+    /// Bytes fed to the parser that may be the start of a reply.
+    held: Vec<u8>,
+    /// Until when the reply to a timed-out command may still arrive.
+    late_reply_until: Option<Instant>,
+}
+
+impl<P: ScannerPort> Link<P> {
+    fn new(port: P, messages: Receiver<ControlMessage>) -> Self {
+        Self {
+            port,
+            messages,
+            deferred: VecDeque::new(),
+            parser: ResponseParser::new(),
+            held: Vec::new(),
+            late_reply_until: None,
+        }
+    }
+
+    /// The next message for the main loop, waiting at most `timeout`. A
+    /// late reply to a timed-out command is removed from received bytes.
+    fn next_message(&mut self, timeout: Duration) -> Option<ControlMessage> {
+        let late_reply_due = self
+            .late_reply_until
+            .is_some_and(|until| Instant::now() < until);
+        if !late_reply_due {
+            self.late_reply_until = None;
+            if !self.held.is_empty() {
+                self.parser.reset();
+                return Some(ControlMessage::Received(std::mem::take(&mut self.held)));
+            }
+        }
+
+        let message = self
+            .deferred
+            .pop_front()
+            .or_else(|| self.messages.recv_timeout(timeout).ok())?;
+        match message {
+            ControlMessage::Received(bytes) if late_reply_due => {
+                let mut scan_bytes = Vec::new();
+                for byte in bytes {
+                    if self.push_byte(byte, &mut scan_bytes).is_some() {
+                        log::debug!("Dropped late GM65 reply");
+                        self.late_reply_until = None;
+                    }
+                }
+                Some(ControlMessage::Received(scan_bytes))
+            }
+            message => Some(message),
+        }
+    }
+
+    /// Discard bytes received but not yet processed, including those
+    /// already forwarded by the reader thread.
+    fn clear_rx(&mut self) -> Result<()> {
+        self.port.clear_rx()?;
+        self.parser.reset();
+        self.held.clear();
+        self.late_reply_until = None;
+        self.deferred
+            .retain(|message| !matches!(message, ControlMessage::Received(_)));
+        while let Ok(message) = self.messages.try_recv() {
+            if !matches!(message, ControlMessage::Received(_)) {
+                self.deferred.push_back(message);
+            }
+        }
+        Ok(())
+    }
+
+    /// Feed one received byte to the reply parser. Bytes that turn out not
+    /// to be part of a reply are moved to `scan_bytes`.
+    fn push_byte(
+        &mut self,
+        byte: u8,
+        scan_bytes: &mut Vec<u8>,
+    ) -> Option<Result<Response, ProtocolError>> {
+        self.held.push(byte);
+        let reply = self.parser.push(byte);
+        if reply.is_some() {
+            self.held.clear();
+        } else {
+            let not_reply = self.held.len() - self.parser.partial_len();
+            scan_bytes.extend(self.held.drain(..not_reply));
+        }
+        reply
+    }
+
+    /// Send one command frame and wait for the matching reply. Bytes that
+    /// aren't part of it and read errors are passed on to the main loop, and
+    /// replies that don't fit the command, such as a late one to an earlier
+    /// command, are skipped.
+    fn execute(&mut self, command: &Command) -> Result<Vec<u8>> {
+        let frame = command.encode()?;
+
+        self.port
+            .write_all(&frame)
+            .map_err(|e| anyhow!("Failed to write GM65 command: {e:?}"))?;
+
+        let deadline = Instant::now() + COMMAND_TIMEOUT;
+        while let Some(remaining) = deadline.checked_duration_since(Instant::now()) {
+            let bytes = match self.messages.recv_timeout(remaining) {
+                Ok(ControlMessage::Received(bytes)) => bytes,
+                // Read errors too, so they are counted and reported.
+                Ok(message) => {
+                    self.deferred.push_back(message);
+                    continue;
+                }
+                Err(_) => break,
+            };
+
+            let mut scan_bytes = Vec::new();
+            let mut result = None;
+            let mut bytes = bytes.into_iter();
+            for byte in bytes.by_ref() {
+                match self.push_byte(byte, &mut scan_bytes) {
+                    Some(Ok(response)) if !command.expects(&response) => {
+                        log::debug!("Skipped GM65 reply not meant for {command:?}");
+                    }
+                    Some(reply) => {
+                        result = Some(reply);
+                        break;
+                    }
+                    None => {}
+                }
+            }
+            scan_bytes.extend(bytes);
+            if !scan_bytes.is_empty() {
+                self.deferred
+                    .push_back(ControlMessage::Received(scan_bytes));
+            }
+            if let Some(reply) = result {
+                self.late_reply_until = None;
+                return Ok(reply?.into_data()?);
+            }
+        }
+
+        self.late_reply_until = Some(Instant::now() + COMMAND_TIMEOUT);
+        bail!("Timed out waiting for GM65 reply")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::mock::MockScanner;
+    use super::*;
+
+    /// Reply to a baud rate read: 9600 baud.
+    const BAUD_REPLY: [u8; 8] = [0x02, 0x00, 0x00, 0x02, 0x39, 0x01, 0xC1, 0x4C];
+    /// Reply to a zone write.
+    const WRITE_ACK: [u8; 7] = [0x02, 0x00, 0x00, 0x01, 0x00, 0x33, 0x31];
+
+    const READ_BAUD: Command = Command::ReadZone {
+        address: zone::BAUD_RATE,
+        len: 2,
+    };
+
+    /// A link whose received bytes come from the test rather than a reader
+    /// thread.
+    fn link() -> (Link<MockScanner>, Sender<ControlMessage>) {
+        let (tx, rx) = mpsc::channel();
+        (Link::new(MockScanner::new(), rx), tx)
+    }
+
+    fn received(bytes: &[u8]) -> ControlMessage {
+        ControlMessage::Received(bytes.to_vec())
+    }
+
+    /// Scan bytes the main loop gets next.
+    fn scan_bytes(link: &mut Link<MockScanner>) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        while let Some(message) = link.next_message(Duration::from_millis(10)) {
+            match message {
+                ControlMessage::Received(chunk) => bytes.extend(chunk),
+                other => panic!("unexpected message {other:?}"),
+            }
+        }
+        bytes
+    }
+
+    #[test]
+    fn passes_on_scan_bytes_around_a_reply() {
+        let (mut link, tx) = link();
+        tx.send(received(b"QAB\x02")).unwrap();
+        let mut chunk = BAUD_REPLY.to_vec();
+        chunk.extend(b"C\r\n");
+        tx.send(received(&chunk)).unwrap();
+
+        assert_eq!(link.execute(&READ_BAUD).unwrap(), [0x39, 0x01]);
+        assert_eq!(scan_bytes(&mut link), b"QAB\x02C\r\n");
+    }
+
+    #[test]
+    fn skips_replies_meant_for_another_command() {
+        let (mut link, tx) = link();
+        let mut chunk = WRITE_ACK.to_vec();
+        chunk.extend(BAUD_REPLY);
+        tx.send(received(&chunk)).unwrap();
+
+        assert_eq!(link.execute(&READ_BAUD).unwrap(), [0x39, 0x01]);
+        assert_eq!(scan_bytes(&mut link), b"");
+    }
+
+    #[test]
+    fn passes_on_read_errors_while_awaiting_a_reply() {
+        let (mut link, tx) = link();
+        tx.send(ControlMessage::ReadError("Timeout".into()))
+            .unwrap();
+        tx.send(received(&BAUD_REPLY)).unwrap();
+
+        assert_eq!(link.execute(&READ_BAUD).unwrap(), [0x39, 0x01]);
+        let message = link.next_message(Duration::from_millis(10));
+        assert!(matches!(message, Some(ControlMessage::ReadError(e)) if e == "Timeout"));
+    }
+
+    #[test]
+    fn drops_a_reply_arriving_after_its_timeout() {
+        let (mut link, tx) = link();
+        assert!(link.execute(&READ_BAUD).is_err());
+
+        let mut chunk = b"Q1".to_vec();
+        chunk.extend(BAUD_REPLY);
+        chunk.extend(b"\r\n");
+        tx.send(received(&chunk)).unwrap();
+        assert_eq!(scan_bytes(&mut link), b"Q1\r\n");
+
+        // Only one reply was owed.
+        tx.send(received(&WRITE_ACK)).unwrap();
+        assert_eq!(scan_bytes(&mut link), WRITE_ACK);
+    }
+
+    #[test]
+    fn releases_held_bytes_once_no_reply_is_due() {
+        let (mut link, tx) = link();
+        assert!(link.execute(&READ_BAUD).is_err());
+
+        // A scan ending in what could be the start of a reply.
+        tx.send(received(b"Q\x02")).unwrap();
+        assert_eq!(scan_bytes(&mut link), b"Q");
+        thread::sleep(COMMAND_TIMEOUT);
+        assert_eq!(scan_bytes(&mut link), b"\x02");
+    }
+}
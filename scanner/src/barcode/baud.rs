@@ -0,0 +1,83 @@
+//! Serial baud rates the GM65 supports (zone bits 0x002A-0x002B).
+
+use std::fmt;
+
+/// A GM65 serial baud rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BaudRate {
+    B1200,
+    B4800,
+    /// Module default.
+    B9600,
+    B14400,
+    B19200,
+    B38400,
+    B57600,
+    B115200,
+}
+
+impl BaudRate {
+    /// All rates in probing order: the factory default first, then the
+    /// fastest, since those are the likeliest settings.
+    pub const PROBE_ORDER: [BaudRate; 8] = [
+        BaudRate::B9600,
+        BaudRate::B115200,
+        BaudRate::B57600,
+        BaudRate::B38400,
+        BaudRate::B19200,
+        BaudRate::B14400,
+        BaudRate::B4800,
+        BaudRate::B1200,
+    ];
+
+    pub fn bps(self) -> u32 {
+        match self {
+            BaudRate::B1200 => 1200,
+            BaudRate::B4800 => 4800,
+            BaudRate::B9600 => 9600,
+            BaudRate::B14400 => 14400,
+            BaudRate::B19200 => 19200,
+            BaudRate::B38400 => 38400,
+            BaudRate::B57600 => 57600,
+            BaudRate::B115200 => 115200,
+        }
+    }
+
+    pub fn from_bps(bps: u32) -> Option<Self> {
+        Self::PROBE_ORDER.into_iter().find(|rate| rate.bps() == bps)
+    }
+
+    /// 13-bit divider stored in the zone bits.
+    fn divider(self) -> u16 {
+        match self {
+            BaudRate::B1200 => 0x09C4,
+            BaudRate::B4800 => 0x0271,
+            BaudRate::B9600 => 0x0139,
+            BaudRate::B14400 => 0x00D0,
+            BaudRate::B19200 => 0x009C,
+            BaudRate::B38400 => 0x004E,
+            BaudRate::B57600 => 0x0034,
+            BaudRate::B115200 => 0x001A,
+        }
+    }
+
+    /// Zone bytes for 0x002A-0x002B; the scanner stores the divider low
+    /// byte first.
+    pub fn zone_bytes(self) -> [u8; 2] {
+        self.divider().to_le_bytes()
+    }
+
+    /// Decode the zone bytes read from 0x002A-0x002B.
+    pub fn from_zone_bytes(bytes: [u8; 2]) -> Option<Self> {
+        let divider = u16::from_le_bytes(bytes) & 0x1FFF;
+        Self::PROBE_ORDER
+            .into_iter()
+            .find(|rate| rate.divider() == divider)
+    }
+}
+
+impl fmt::Display for BaudRate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} baud", self.bps())
+    }
+}
@@ -0,0 +1,321 @@
+//! Check digit validation for retail and industrial symbologies.
+//!
+//! The GM65 transmits EAN/UPC check digits by default, so a payload with the
+//! wrong length or a mismatching check digit means the digit was stripped or
+//! misread. ITF is only checked in its 14-digit ITF-14 form and Code 39 only
+//! when the labels are known to carry a mod 43 check character, since both
+//! are optional for those symbologies.
+
+use std::fmt;
+
+use super::gs1::Gs1Message;
+use super::Symbology;
+
+/// Code 39 characters in mod 43 value order.
+const CODE39_CHARSET: &[u8; 43] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ-. $/+%";
+
+/// GS1 AIs whose value is a fixed-length number ending in a mod 10 check
+/// digit: SSCC, GTIN, content GTIN, GLNs and GSRNs.
+const GS1_CHECKED_AIS: [&str; 12] = [
+    "00", "01", "02", "410", "411", "412", "413", "414", "415", "416", "8017", "8018",
+];
+
+/// Why a scan failed check digit validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CheckDigitError {
+    /// Payload length doesn't fit the symbology, e.g. the check digit was
+    /// stripped.
+    WrongLength { expected: usize, actual: usize },
+    /// Payload contains a character not allowed in the symbology.
+    InvalidCharacter(char),
+    /// Check digit doesn't match the data.
+    Mismatch { expected: char, actual: char },
+    /// A GS1 element's check digit doesn't match its data.
+    Gs1Mismatch {
+        ai: String,
+        expected: char,
+        actual: char,
+    },
+}
+
+impl fmt::Display for CheckDigitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckDigitError::WrongLength { expected, actual } => {
+                write!(f, "expected {expected} characters, got {actual}")
+            }
+            CheckDigitError::InvalidCharacter(c) => write!(f, "invalid character {c:?}"),
+            CheckDigitError::Mismatch { expected, actual } => {
+                write!(f, "check digit is {actual}, expected {expected}")
+            }
+            CheckDigitError::Gs1Mismatch {
+                ai,
+                expected,
+                actual,
+            } => write!(f, "AI ({ai}) check digit is {actual}, expected {expected}"),
+        }
+    }
+}
+
+impl std::error::Error for CheckDigitError {}
+
+/// Validate the check digit of a payload in the given symbology.
+///
+/// Symbologies without a mandatory check digit always pass.
+pub fn validate(symbology: Symbology, payload: &str) -> Result<(), CheckDigitError> {
+    match symbology {
+        Symbology::Ean13 => check_mod10(payload, 13),
+        Symbology::Ean8 => check_mod10(payload, 8),
+        Symbology::UpcA => check_mod10(payload, 12),
+        Symbology::UpcE0 | Symbology::UpcE1 => check_upce(payload),
+        Symbology::Interleaved2of5 if payload.len() == 14 => check_mod10(payload, 14),
+        _ => Ok(()),
+    }
+}
+
+/// Validate a trailing Code 39 mod 43 check character.
+pub fn validate_code39_mod43(payload: &str) -> Result<(), CheckDigitError> {
+    let (data, check) = match payload.as_bytes() {
+        [data @ .., check] if !data.is_empty() => (data, *check),
+        _ => {
+            return Err(CheckDigitError::WrongLength {
+                expected: 2,
+                actual: payload.len(),
+            })
+        }
+    };
+
+    let value = |c: u8| {
+        CODE39_CHARSET
+            .iter()
+            .position(|&x| x == c)
+            .ok_or(CheckDigitError::InvalidCharacter(c as char))
+    };
+    let mut sum = 0;
+    for &c in data {
+        sum += value(c)?;
+    }
+    value(check)?;
+
+    let expected = CODE39_CHARSET[sum % 43];
+    if check != expected {
+        return Err(CheckDigitError::Mismatch {
+            expected: expected as char,
+            actual: check as char,
+        });
+    }
+    Ok(())
+}
+
+/// Validate the check digits of GS1 elements that carry one.
+pub fn validate_gs1(message: &Gs1Message) -> Result<(), CheckDigitError> {
+    for element in &message.elements {
+        if !GS1_CHECKED_AIS.contains(&element.ai.as_str()) {
+            continue;
+        }
+        check_mod10(&element.value, element.value.len()).map_err(|e| match e {
+            CheckDigitError::Mismatch { expected, actual } => CheckDigitError::Gs1Mismatch {
+                ai: element.ai.clone(),
+                expected,
+                actual,
+            },
+            e => e,
+        })?;
+    }
+    Ok(())
+}
+
+/// GS1 mod 10 check digit for `digits`: weights 3 and 1 alternate from the
+/// rightmost digit.
+fn gs1_mod10(digits: &[u8]) -> u8 {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| (d - b'0') as u32 * if i % 2 == 0 { 3 } else { 1 })
+        .sum();
+    b'0' + ((10 - sum % 10) % 10) as u8
+}
+
+/// Check a `len`-digit number whose last digit is a GS1 mod 10 check digit.
+fn check_mod10(payload: &str, len: usize) -> Result<(), CheckDigitError> {
+    let digits = payload.as_bytes();
+    if let Some(&c) = digits.iter().find(|c| !c.is_ascii_digit()) {
+        return Err(CheckDigitError::InvalidCharacter(c as char));
+    }
+    if digits.len() != len || len < 2 {
+        return Err(CheckDigitError::WrongLength {
+            expected: len,
+            actual: digits.len(),
+        });
+    }
+
+    let (data, check) = digits.split_at(len - 1);
+    let expected = gs1_mod10(data);
+    if check[0] != expected {
+        return Err(CheckDigitError::Mismatch {
+            expected: expected as char,
+            actual: check[0] as char,
+        });
+    }
+    Ok(())
+}
+
+/// Check a UPC-E code: number system, six data digits and the check digit
+/// of the equivalent UPC-A code.
+fn check_upce(payload: &str) -> Result<(), CheckDigitError> {
+    let d = payload.as_bytes();
+    if let Some(&c) = d.iter().find(|c| !c.is_ascii_digit()) {
+        return Err(CheckDigitError::InvalidCharacter(c as char));
+    }
+    if d.len() != 8 {
+        return Err(CheckDigitError::WrongLength {
+            expected: 8,
+            actual: d.len(),
+        });
+    }
+
+    // Zero-suppression rules, keyed on the last data digit.
+    let upca: [u8; 11] = match d[6] {
+        b'0'..=b'2' => [
+            d[0], d[1], d[2], d[6], b'0', b'0', b'0', b'0', d[3], d[4], d[5],
+        ],
+        b'3' => [
+            d[0], d[1], d[2], d[3], b'0', b'0', b'0', b'0', b'0', d[4], d[5],
+        ],
+        b'4' => [
+            d[0], d[1], d[2], d[3], d[4], b'0', b'0', b'0', b'0', b'0', d[5],
+        ],
+        _ => [
+            d[0], d[1], d[2], d[3], d[4], d[5], b'0', b'0', b'0', b'0', d[6],
+        ],
+    };
+
+    let expected = gs1_mod10(&upca);
+    if d[7] != expected {
+        return Err(CheckDigitError::Mismatch {
+            expected: expected as char,
+            actual: d[7] as char,
+        });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::gs1;
+    use super::*;
+
+    fn mismatch(expected: char, actual: char) -> Result<(), CheckDigitError> {
+        Err(CheckDigitError::Mismatch { expected, actual })
+    }
+
+    #[test]
+    fn ean13() {
+        assert_eq!(validate(Symbology::Ean13, "4006381333931"), Ok(()));
+        assert_eq!(
+            validate(Symbology::Ean13, "4006381332931"),
+            mismatch('4', '1')
+        );
+        assert_eq!(
+            validate(Symbology::Ean13, "400638133393"),
+            Err(CheckDigitError::WrongLength {
+                expected: 13,
+                actual: 12
+            })
+        );
+    }
+
+    #[test]
+    fn ean8() {
+        assert_eq!(validate(Symbology::Ean8, "96385074"), Ok(()));
+        assert_eq!(validate(Symbology::Ean8, "96386074"), mismatch('1', '4'));
+    }
+
+    #[test]
+    fn upc_a() {
+        assert_eq!(validate(Symbology::UpcA, "036000291452"), Ok(()));
+        assert_eq!(
+            validate(Symbology::UpcA, "036000291752"),
+            mismatch('9', '2')
+        );
+        assert_eq!(
+            validate(Symbology::UpcA, "03600029145X"),
+            Err(CheckDigitError::InvalidCharacter('X'))
+        );
+    }
+
+    #[test]
+    fn upc_e_expands_each_zero_suppression_form() {
+        // Last data digit 0-2, 3, 4 and 5-9, each checked as its UPC-A form:
+        // 04210000526, 01230000045, 01234000001, 01234500005, 12345600007.
+        for code in ["04252614", "01234531", "01234145", "01234558", "12345670"] {
+            assert_eq!(validate(Symbology::UpcE0, code), Ok(()), "{code}");
+        }
+        assert_eq!(validate(Symbology::UpcE1, "12345670"), Ok(()));
+
+        assert_eq!(validate(Symbology::UpcE0, "04252624"), mismatch('3', '4'));
+        assert_eq!(validate(Symbology::UpcE0, "01234631"), mismatch('8', '1'));
+        assert_eq!(validate(Symbology::UpcE0, "01235145"), mismatch('2', '5'));
+        assert_eq!(validate(Symbology::UpcE0, "01234568"), mismatch('5', '8'));
+        assert_eq!(validate(Symbology::UpcE1, "12345770"), mismatch('9', '0'));
+    }
+
+    #[test]
+    fn itf14() {
+        assert_eq!(
+            validate(Symbology::Interleaved2of5, "10012345678902"),
+            Ok(())
+        );
+        assert_eq!(
+            validate(Symbology::Interleaved2of5, "10012345678912"),
+            mismatch('9', '2')
+        );
+        // Other ITF lengths carry no mandatory check digit.
+        assert_eq!(validate(Symbology::Interleaved2of5, "123456"), Ok(()));
+    }
+
+    #[test]
+    fn code39_mod43() {
+        assert_eq!(validate_code39_mod43("CODE39W"), Ok(()));
+        assert_eq!(validate_code39_mod43("ABC-123W"), Ok(()));
+        assert_eq!(validate_code39_mod43("CODE38W"), mismatch('V', 'W'));
+        assert_eq!(
+            validate_code39_mod43("code39W"),
+            Err(CheckDigitError::InvalidCharacter('c'))
+        );
+        // Without the option the check character isn't looked for.
+        assert_eq!(validate(Symbology::Code39, "CODE38W"), Ok(()));
+    }
+
+    fn gs1_result(payload: &str) -> Result<(), CheckDigitError> {
+        validate_gs1(&gs1::parse(payload, None).unwrap())
+    }
+
+    #[test]
+    fn gs1_sscc_gtin_and_gln() {
+        assert_eq!(gs1_result("(00)376123450000012347"), Ok(()));
+        assert_eq!(gs1_result("(01)10012345678902(10)LOT7"), Ok(()));
+        assert_eq!(gs1_result("(414)0614141000005"), Ok(()));
+
+        let gs1_mismatch = |ai: &str, expected, actual| {
+            Err(CheckDigitError::Gs1Mismatch {
+                ai: ai.to_string(),
+                expected,
+                actual,
+            })
+        };
+        assert_eq!(
+            gs1_result("(00)376123450000013347"),
+            gs1_mismatch("00", '4', '7')
+        );
+        assert_eq!(
+            gs1_result("(01)10012345678912(10)LOT7"),
+            gs1_mismatch("01", '9', '2')
+        );
+        assert_eq!(
+            gs1_result("(414)0614141001005"),
+            gs1_mismatch("414", '2', '5')
+        );
+    }
+}
@@ -0,0 +1,718 @@
+//! Classification of common QR payload formats: web links, `WIFI:` network
+//! credentials, vCard and MECARD contacts and `geo:` locations.
+//!
+//! Prefixes are matched case-insensitively, as generators differ. A payload
+//! that starts like one of the formats but doesn't parse is an error rather
+//! than plain text, so a damaged Wi-Fi code isn't silently shown as text.
+
+use std::borrow::Cow;
+use std::fmt;
+
+/// What a scanned payload contains.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScanContent {
+    /// An `http://` or `https://` URL.
+    Url(WebLink),
+    /// Wi-Fi network credentials (`WIFI:S:...;T:...;P:...;;`).
+    Wifi(WifiNetwork),
+    /// A vCard (`BEGIN:VCARD`) or MECARD (`MECARD:`) contact.
+    Contact(Contact),
+    /// A location (`geo:lat,lon`, RFC 5870).
+    Geo(GeoLocation),
+    /// Anything else.
+    Text,
+}
+
+impl ScanContent {
+    /// Classify `payload`.
+    pub fn parse(payload: &str) -> Result<Self, ContentError> {
+        let payload = payload.trim();
+
+        if let Some(rest) = strip_prefix_ignore_case(payload, "WIFI:") {
+            parse_wifi(rest).map(ScanContent::Wifi)
+        } else if let Some(rest) = strip_prefix_ignore_case(payload, "MECARD:") {
+            parse_mecard(rest).map(ScanContent::Contact)
+        } else if strip_prefix_ignore_case(payload, "BEGIN:VCARD").is_some() {
+            parse_vcard(payload).map(ScanContent::Contact)
+        } else if let Some(rest) = strip_prefix_ignore_case(payload, "geo:") {
+            parse_geo(rest).map(ScanContent::Geo)
+        } else if ["http://", "https://"]
+            .iter()
+            .any(|scheme| strip_prefix_ignore_case(payload, scheme).is_some())
+        {
+            parse_url(payload).map(ScanContent::Url)
+        } else {
+            Ok(ScanContent::Text)
+        }
+    }
+
+    /// Short name of the content type, e.g. `"url"`.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScanContent::Url(_) => "url",
+            ScanContent::Wifi(_) => "wifi",
+            ScanContent::Contact(_) => "contact",
+            ScanContent::Geo(_) => "geo",
+            ScanContent::Text => "text",
+        }
+    }
+}
+
+/// `payload` with the password of a `WIFI:` code masked, for logs and the
+/// scan history. Other payloads are returned unchanged.
+pub fn redact_wifi_password(payload: &str) -> Cow<'_, str> {
+    let Some(body) = strip_prefix_ignore_case(payload, "WIFI:") else {
+        return Cow::Borrowed(payload);
+    };
+    let fields: Vec<String> = split_unescaped(body, ';')
+        .into_iter()
+        .map(|field| match field.split_once(':') {
+            Some((key, value)) if key.trim().eq_ignore_ascii_case("P") && !value.is_empty() => {
+                format!("{key}:***")
+            }
+            _ => field.to_string(),
+        })
+        .collect();
+    Cow::Owned(format!("{}{}", &payload[..5], fields.join(";")))
+}
+
+/// A web link.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebLink {
+    pub url: String,
+    /// Host name without port or user info, lowercased.
+    pub host: String,
+}
+
+impl WebLink {
+    /// Host without a leading `www.`, for display.
+    pub fn domain(&self) -> &str {
+        self.host.strip_prefix("www.").unwrap_or(&self.host)
+    }
+}
+
+/// Authentication of a Wi-Fi network (`T:` field).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WifiSecurity {
+    /// No password (`nopass` or no `T:` field).
+    Open,
+    Wep,
+    /// WPA or WPA2 personal.
+    Wpa,
+    /// WPA3 personal.
+    Sae,
+}
+
+impl WifiSecurity {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WifiSecurity::Open => "open",
+            WifiSecurity::Wep => "WEP",
+            WifiSecurity::Wpa => "WPA",
+            WifiSecurity::Sae => "WPA3",
+        }
+    }
+}
+
+/// Credentials from a `WIFI:` code.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WifiNetwork {
+    pub ssid: String,
+    pub security: WifiSecurity,
+    /// Empty for open networks.
+    pub password: String,
+    /// The network doesn't broadcast its SSID.
+    pub hidden: bool,
+}
+
+/// A contact card. Fields missing from the card are empty.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Contact {
+    /// Formatted name, e.g. `"Jane Doe"`.
+    pub name: String,
+    pub organization: String,
+    pub title: String,
+    pub phones: Vec<String>,
+    pub emails: Vec<String>,
+    pub urls: Vec<String>,
+    /// Postal address on one line.
+    pub address: String,
+    pub note: String,
+}
+
+/// A point from a `geo:` URI, in WGS-84 degrees.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GeoLocation {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Meters.
+    pub altitude: Option<f64>,
+    /// Place name or search text from a `?q=` query.
+    pub query: Option<String>,
+}
+
+/// Reasons a payload with a recognized prefix could not be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContentError {
+    /// The Wi-Fi code has no `S:` field.
+    MissingSsid,
+    /// The Wi-Fi code has an unsupported `T:` value, such as WPA2-EAP.
+    UnsupportedSecurity(String),
+    /// The contact has no name, phone, email or organization.
+    EmptyContact,
+    /// The vCard has no `END:VCARD` line.
+    UnterminatedVcard,
+    /// Latitude or longitude is missing, not a number or out of range.
+    InvalidCoordinates,
+    /// The URL has no host.
+    MissingHost,
+}
+
+impl fmt::Display for ContentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ContentError::MissingSsid => write!(f, "Wi-Fi code without network name"),
+            ContentError::UnsupportedSecurity(kind) => {
+                write!(f, "unsupported Wi-Fi security {kind:?}")
+            }
+            ContentError::EmptyContact => write!(f, "contact without name or details"),
+            ContentError::UnterminatedVcard => write!(f, "vCard without END:VCARD"),
+            ContentError::InvalidCoordinates => write!(f, "invalid geo coordinates"),
+            ContentError::MissingHost => write!(f, "URL without host"),
+        }
+    }
+}
+
+impl std::error::Error for ContentError {}
+
+fn strip_prefix_ignore_case<'a>(s: &'a str, prefix: &str) -> Option<&'a str> {
+    let head = s.get(..prefix.len())?;
+    head.eq_ignore_ascii_case(prefix)
+        .then(|| &s[prefix.len()..])
+}
+
+fn parse_url(url: &str) -> Result<WebLink, ContentError> {
+    let (_, rest) = url.split_once("://").ok_or(ContentError::MissingHost)?;
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or("");
+    let host_port = authority.rsplit('@').next().unwrap_or("");
+    // An IPv6 literal keeps its brackets; otherwise drop the port.
+    let host = match host_port.strip_prefix('[') {
+        Some(literal) => literal.split(']').next().unwrap_or(""),
+        None => host_port.split(':').next().unwrap_or(""),
+    };
+    if host.is_empty() {
+        return Err(ContentError::MissingHost);
+    }
+
+    Ok(WebLink {
+        url: url.to_string(),
+        host: host.to_ascii_lowercase(),
+    })
+}
+
+/// Split `s` on `separator` where it isn't escaped with a backslash. The
+/// escapes are left in place.
+fn split_unescaped(s: &str, separator: char) -> Vec<&str> {
+    let mut fields = Vec::new();
+    let mut start = 0;
+    let mut escaped = false;
+
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            c if c == separator => {
+                fields.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    fields.push(&s[start..]);
+    fields
+}
+
+/// Remove the backslash escapes used by `WIFI:` and MECARD fields.
+fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// `KEY:value` pairs of a `WIFI:` or MECARD body, keys uppercased.
+fn key_values(body: &str) -> impl Iterator<Item = (String, String)> + '_ {
+    split_unescaped(body, ';').into_iter().filter_map(|field| {
+        let (key, value) = field.split_once(':')?;
+        Some((key.trim().to_ascii_uppercase(), unescape(value)))
+    })
+}
+
+fn parse_wifi(body: &str) -> Result<WifiNetwork, ContentError> {
+    let mut ssid = None;
+    let mut security = None;
+    let mut password = String::new();
+    let mut hidden = false;
+
+    for (key, value) in key_values(body) {
+        match key.as_str() {
+            "S" => ssid = Some(value),
+            "T" => security = Some(value),
+            "P" => password = value,
+            "H" => hidden = value.eq_ignore_ascii_case("true"),
+            _ => {}
+        }
+    }
+
+    let ssid = ssid
+        .filter(|ssid| !ssid.is_empty())
+        .ok_or(ContentError::MissingSsid)?;
+    let security = match security.as_deref().map(str::to_ascii_uppercase).as_deref() {
+        None | Some("" | "NOPASS") => WifiSecurity::Open,
+        Some("WEP") => WifiSecurity::Wep,
+        Some("WPA" | "WPA2") => WifiSecurity::Wpa,
+        Some("SAE" | "WPA3") => WifiSecurity::Sae,
+        Some(other) => return Err(ContentError::UnsupportedSecurity(other.to_string())),
+    };
+
+    Ok(WifiNetwork {
+        ssid,
+        password: if security == WifiSecurity::Open {
+            String::new()
+        } else {
+            password
+        },
+        security,
+        hidden,
+    })
+}
+
+fn parse_mecard(body: &str) -> Result<Contact, ContentError> {
+    let mut contact = Contact::default();
+
+    for (key, value) in key_values(body) {
+        let value = value.trim().to_string();
+        if value.is_empty() {
+            continue;
+        }
+        match key.as_str() {
+            // `N:Doe,Jane` is family name first.
+            "N" => {
+                contact.name = match value.split_once(',') {
+                    Some((family, given)) => join_name(&[given, family]),
+                    None => value,
+                }
+            }
+            "ORG" => contact.organization = value,
+            "TEL" => contact.phones.push(value),
+            "EMAIL" => contact.emails.push(value),
+            "URL" => contact.urls.push(value),
+            "ADR" => contact.address = value,
+            "NOTE" => contact.note = value,
+            _ => {}
+        }
+    }
+
+    non_empty(contact)
+}
+
+fn parse_vcard(card: &str) -> Result<Contact, ContentError> {
+    let mut contact = Contact::default();
+    let mut structured_name = String::new();
+    let mut terminated = false;
+
+    for line in unfold_vcard(card) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        // `item1.TEL;TYPE=CELL` → property `TEL` with parameters.
+        let mut params = name.split(';');
+        let property = params.next().unwrap_or("");
+        let property = property
+            .rsplit('.')
+            .next()
+            .unwrap_or("")
+            .to_ascii_uppercase();
+        let quoted_printable = params.any(|param| {
+            param.eq_ignore_ascii_case("ENCODING=QUOTED-PRINTABLE")
+                || param.eq_ignore_ascii_case("QUOTED-PRINTABLE")
+        });
+        let value = if quoted_printable {
+            decode_hex_escapes(value, b'=')
+        } else {
+            value.to_string()
+        };
+
+        match property.as_str() {
+            "FN" => contact.name = vcard_text(&value),
+            "N" => structured_name = value,
+            "ORG" => contact.organization = join_components(&value, " "),
+            "TITLE" => contact.title = vcard_text(&value),
+            "TEL" => contact.phones.push(vcard_text(&value)),
+            "EMAIL" => contact.emails.push(vcard_text(&value)),
+            "URL" => contact.urls.push(vcard_text(&value)),
+            "ADR" => contact.address = join_components(&value, ", "),
+            "NOTE" => contact.note = vcard_text(&value),
+            "END" if value.trim().eq_ignore_ascii_case("VCARD") => {
+                terminated = true;
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    if !terminated {
+        return Err(ContentError::UnterminatedVcard);
+    }
+    // `N:Doe;Jane;;Dr.;` is family;given;additional;prefix;suffix.
+    if contact.name.is_empty() {
+        let parts: Vec<String> = split_unescaped(&structured_name, ';')
+            .into_iter()
+            .map(vcard_text)
+            .collect();
+        let part = |i: usize| parts.get(i).map_or("", String::as_str);
+        contact.name = join_name(&[part(3), part(1), part(2), part(0), part(4)]);
+    }
+    contact.phones.retain(|phone| !phone.is_empty());
+    contact.emails.retain(|email| !email.is_empty());
+    contact.urls.retain(|url| !url.is_empty());
+
+    non_empty(contact)
+}
+
+/// Lines of a vCard with folded lines joined: continuation lines start with
+/// a space or tab, and vCard 2.1 quoted-printable lines end with `=`.
+fn unfold_vcard(card: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for line in card.lines() {
+        let line = line.trim_end_matches('\r');
+        let soft_break = lines.last().is_some_and(|last| {
+            last.ends_with('=') && last.to_ascii_uppercase().contains("QUOTED-PRINTABLE")
+        });
+        match lines.last_mut() {
+            Some(last) if line.starts_with([' ', '\t']) => last.push_str(&line[1..]),
+            Some(last) if soft_break => {
+                last.pop();
+                last.push_str(line);
+            }
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+/// Unescape a vCard text value (`\n`, `\,`, `\;`, `\\`).
+fn vcard_text(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some('n' | 'N') => out.push(' '),
+                Some(escaped) => out.push(escaped),
+                None => {}
+            },
+            c => out.push(c),
+        }
+    }
+    out.trim().to_string()
+}
+
+/// Join the non-empty `;`-separated components of a structured value.
+fn join_components(value: &str, separator: &str) -> String {
+    split_unescaped(value, ';')
+        .into_iter()
+        .map(vcard_text)
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(separator)
+}
+
+fn join_name(parts: &[&str]) -> String {
+    parts
+        .iter()
+        .map(|part| part.trim())
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Decode `=XX` (quoted-printable) or `%XX` (URI) escapes, introduced by
+/// `marker`; the bytes are taken as UTF-8.
+fn decode_hex_escapes(value: &str, marker: u8) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let escaped = bytes
+            .get(i + 1..i + 3)
+            .filter(|_| bytes[i] == marker)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                out.push(byte);
+                i += 3;
+            }
+            None => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn non_empty(contact: Contact) -> Result<Contact, ContentError> {
+    let empty = contact.name.is_empty()
+        && contact.organization.is_empty()
+        && contact.phones.is_empty()
+        && contact.emails.is_empty();
+    if empty {
+        Err(ContentError::EmptyContact)
+    } else {
+        Ok(contact)
+    }
+}
+
+/// Parse `lat,lon[,alt][;params][?q=query]`.
+fn parse_geo(uri: &str) -> Result<GeoLocation, ContentError> {
+    let (point, query) = match uri.split_once('?') {
+        Some((point, query)) => (point, Some(query)),
+        None => (uri, None),
+    };
+    let coordinates = point.split(';').next().unwrap_or("");
+    let mut numbers = coordinates.split(',').map(|n| n.trim().parse::<f64>().ok());
+
+    let (Some(Some(latitude)), Some(Some(longitude))) = (numbers.next(), numbers.next()) else {
+        return Err(ContentError::InvalidCoordinates);
+    };
+    let altitude = match numbers.next() {
+        Some(Some(altitude)) => Some(altitude),
+        Some(None) => return Err(ContentError::InvalidCoordinates),
+        None => None,
+    };
+    if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+        return Err(ContentError::InvalidCoordinates);
+    }
+
+    // Maps apps write the place as `?q=Name` or `?q=lat,lon(Name)`.
+    let query = query
+        .and_then(|query| query.split('&').find_map(|pair| pair.strip_prefix("q=")))
+        .map(|q| decode_hex_escapes(&q.replace('+', " "), b'%'))
+        .filter(|q| !q.is_empty());
+
+    Ok(GeoLocation {
+        latitude,
+        longitude,
+        altitude,
+        query,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wifi(payload: &str) -> WifiNetwork {
+        match ScanContent::parse(payload) {
+            Ok(ScanContent::Wifi(network)) => network,
+            other => panic!("{payload:?} parsed as {other:?}"),
+        }
+    }
+
+    fn contact(payload: &str) -> Contact {
+        match ScanContent::parse(payload) {
+            Ok(ScanContent::Contact(contact)) => contact,
+            other => panic!("{payload:?} parsed as {other:?}"),
+        }
+    }
+
+    fn geo(payload: &str) -> GeoLocation {
+        match ScanContent::parse(payload) {
+            Ok(ScanContent::Geo(location)) => location,
+            other => panic!("{payload:?} parsed as {other:?}"),
+        }
+    }
+
+    fn url(payload: &str) -> WebLink {
+        match ScanContent::parse(payload) {
+            Ok(ScanContent::Url(link)) => link,
+            other => panic!("{payload:?} parsed as {other:?}"),
+        }
+    }
+
+    #[test]
+    fn wifi_with_escaped_separators() {
+        let network = wifi(r#"WIFI:T:WPA;S:Cafe\;Bar;P:pa\:ss\;wo\\rd;;"#);
+        assert_eq!(network.ssid, "Cafe;Bar");
+        assert_eq!(network.password, r"pa:ss;wo\rd");
+        assert_eq!(network.security, WifiSecurity::Wpa);
+        assert!(!network.hidden);
+    }
+
+    #[test]
+    fn wifi_hidden_network() {
+        let network = wifi("wifi:S:Backroom;T:SAE;P:secret;H:true;;");
+        assert_eq!(network.ssid, "Backroom");
+        assert_eq!(network.security, WifiSecurity::Sae);
+        assert!(network.hidden);
+    }
+
+    #[test]
+    fn wifi_without_password() {
+        let network = wifi("WIFI:S:Guest;T:nopass;P:ignored;;");
+        assert_eq!(network.security, WifiSecurity::Open);
+        assert_eq!(network.password, "");
+
+        assert_eq!(wifi("WIFI:S:Guest;;").security, WifiSecurity::Open);
+    }
+
+    #[test]
+    fn wifi_errors() {
+        assert_eq!(
+            ScanContent::parse("WIFI:T:WPA;P:secret;;"),
+            Err(ContentError::MissingSsid)
+        );
+        assert_eq!(
+            ScanContent::parse("WIFI:S:Office;T:WPA2-EAP;;"),
+            Err(ContentError::UnsupportedSecurity("WPA2-EAP".into()))
+        );
+    }
+
+    #[test]
+    fn wifi_passwords_are_redacted() {
+        assert_eq!(
+            redact_wifi_password(r"wifi:T:WPA;S:Cafe\;Bar;P:pa\:ss\;word;H:true;;"),
+            r"wifi:T:WPA;S:Cafe\;Bar;P:***;H:true;;"
+        );
+        // Even in codes that don't parse.
+        assert_eq!(redact_wifi_password("WIFI:p:secret;;"), "WIFI:p:***;;");
+        assert_eq!(
+            redact_wifi_password("WIFI:S:Guest;P:;;"),
+            "WIFI:S:Guest;P:;;"
+        );
+        assert_eq!(redact_wifi_password("P:secret"), "P:secret");
+    }
+
+    #[test]
+    fn vcard_3_with_folded_lines() {
+        let card = "BEGIN:VCARD\r\n\
+                    VERSION:3.0\r\n\
+                    N:Doe;Jane;;Dr.;\r\n\
+                    ORG:Example\\, Inc.;Research\r\n\
+                    TEL;TYPE=CELL:+1 555 0100\r\n\
+                    item1.EMAIL;TYPE=INTERNET:jane@exam\r\n \
+                    ple.com\r\n\
+                    NOTE:First line\\nsecond\r\n\
+                    \tline\r\n\
+                    END:VCARD\r\n";
+        let contact = contact(card);
+        assert_eq!(contact.name, "Dr. Jane Doe");
+        assert_eq!(contact.organization, "Example, Inc. Research");
+        assert_eq!(contact.phones, ["+1 555 0100"]);
+        assert_eq!(contact.emails, ["jane@example.com"]);
+        assert_eq!(contact.note, "First line secondline");
+    }
+
+    #[test]
+    fn vcard_2_1_with_quoted_printable() {
+        let card = "BEGIN:VCARD\n\
+                    VERSION:2.1\n\
+                    FN;ENCODING=QUOTED-PRINTABLE;CHARSET=UTF-8:J=C3=BCrgen M=C3=BC=\n\
+                    ller\n\
+                    TEL;WORK;VOICE:030 1234\n\
+                    ADR;HOME:;;Hauptstr. 1;Berlin;;10115;Germany\n\
+                    END:VCARD";
+        let contact = contact(card);
+        assert_eq!(contact.name, "Jürgen Müller");
+        assert_eq!(contact.phones, ["030 1234"]);
+        assert_eq!(contact.address, "Hauptstr. 1, Berlin, 10115, Germany");
+    }
+
+    #[test]
+    fn vcard_errors() {
+        assert_eq!(
+            ScanContent::parse("BEGIN:VCARD\nFN:Jane Doe\n"),
+            Err(ContentError::UnterminatedVcard)
+        );
+        assert_eq!(
+            ScanContent::parse("BEGIN:VCARD\nVERSION:3.0\nEND:VCARD"),
+            Err(ContentError::EmptyContact)
+        );
+    }
+
+    #[test]
+    fn mecard() {
+        let contact = contact(
+            r"MECARD:N:Doe,Jane;TEL:5550100;TEL:5550101;EMAIL:jane@example.com;NOTE:a\;b;;",
+        );
+        assert_eq!(contact.name, "Jane Doe");
+        assert_eq!(contact.phones, ["5550100", "5550101"]);
+        assert_eq!(contact.emails, ["jane@example.com"]);
+        assert_eq!(contact.note, "a;b");
+
+        assert_eq!(
+            ScanContent::parse("MECARD:;;"),
+            Err(ContentError::EmptyContact)
+        );
+    }
+
+    #[test]
+    fn geo_uris() {
+        let location = geo("geo:52.5163,13.3777");
+        assert_eq!(location.latitude, 52.5163);
+        assert_eq!(location.longitude, 13.3777);
+        assert_eq!(location.altitude, None);
+        assert_eq!(location.query, None);
+
+        let location = geo("GEO:-33.8568,151.2153,12.5;u=10?q=Sydney+Opera%20House");
+        assert_eq!(location.latitude, -33.8568);
+        assert_eq!(location.altitude, Some(12.5));
+        assert_eq!(location.query.as_deref(), Some("Sydney Opera House"));
+
+        for invalid in ["geo:91,0", "geo:0,181", "geo:52.5", "geo:north,east"] {
+            assert_eq!(
+                ScanContent::parse(invalid),
+                Err(ContentError::InvalidCoordinates),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn urls() {
+        let link = url("https://www.example.com/path?q=1");
+        assert_eq!(link.host, "www.example.com");
+        assert_eq!(link.domain(), "example.com");
+
+        let link = url("http://user:pw@Example.org:8080/");
+        assert_eq!(link.host, "example.org");
+
+        let link = url("HTTPS://EXAMPLE.NET");
+        assert_eq!(link.url, "HTTPS://EXAMPLE.NET");
+        assert_eq!(link.host, "example.net");
+
+        assert_eq!(url("http://[::1]:80/").host, "::1");
+        assert_eq!(
+            ScanContent::parse("https:///path"),
+            Err(ContentError::MissingHost)
+        );
+    }
+
+    #[test]
+    fn other_payloads_are_text() {
+        for text in ["Hello", "ftp://example.com", "4006381333931", "WIFI"] {
+            assert_eq!(ScanContent::parse(text), Ok(ScanContent::Text), "{text}");
+        }
+    }
+}
@@ -0,0 +1,73 @@
+//! Suppressing repeat reads of the same label.
+//!
+//! In continuous mode the GM65 reports a label many times a second for as
+//! long as it stays in view. [`Deduplicator`] drops a read that matches the
+//! previous one (same payload and symbology) within the window. Each repeat
+//! restarts the window, so a label held under the scanner is reported once.
+
+use std::time::{Duration, Instant};
+
+use super::Symbology;
+
+/// The most recently reported read.
+struct LastRead {
+    symbology: Option<Symbology>,
+    payload: Vec<u8>,
+    seen_at: Instant,
+}
+
+/// Repeat-read filter; a zero window lets every read through.
+pub struct Deduplicator {
+    window: Duration,
+    last: Option<LastRead>,
+    repeats: u32,
+}
+
+impl Deduplicator {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            last: None,
+            repeats: 0,
+        }
+    }
+
+    /// Change the window; the next read is always reported.
+    pub fn set_window(&mut self, window: Duration) {
+        self.window = window;
+        self.last = None;
+        self.repeats = 0;
+    }
+
+    /// Record a read. Returns `false` if it repeats the previous read within
+    /// the window and should be dropped.
+    pub fn check(&mut self, symbology: Option<Symbology>, payload: &[u8], now: Instant) -> bool {
+        if self.window.is_zero() {
+            return true;
+        }
+
+        if let Some(last) = &mut self.last {
+            let repeat = last.symbology == symbology
+                && last.payload == payload
+                && now.duration_since(last.seen_at) < self.window;
+            if repeat {
+                last.seen_at = now;
+                self.repeats += 1;
+                return false;
+            }
+        }
+
+        self.last = Some(LastRead {
+            symbology,
+            payload: payload.to_vec(),
+            seen_at: now,
+        });
+        self.repeats = 0;
+        true
+    }
+
+    /// Reads dropped since the current label was last reported.
+    pub fn repeats(&self) -> u32 {
+        self.repeats
+    }
+}
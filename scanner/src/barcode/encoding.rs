@@ -0,0 +1,72 @@
+//! Character encodings the GM65 uses for scan output (zone bit 0x000D bits 3-2).
+
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+
+/// How the scanner encodes non-ASCII payload text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PayloadEncoding {
+    /// Simplified Chinese GBK (module default).
+    #[default]
+    Gbk,
+    /// Payload bytes passed through unchanged; decoded as UTF-8, which is
+    /// what QR codes carry in practice.
+    Unicode,
+    /// Traditional Chinese BIG5.
+    Big5,
+}
+
+impl PayloadEncoding {
+    pub const MASK: u8 = 0b0000_1100;
+
+    pub fn bits(self) -> u8 {
+        match self {
+            PayloadEncoding::Gbk => 0b00 << 2,
+            PayloadEncoding::Unicode => 0b01 << 2,
+            PayloadEncoding::Big5 => 0b10 << 2,
+        }
+    }
+
+    /// Decode from the zone byte; the reserved value `11` falls back to GBK.
+    pub fn from_bits(bits: u8) -> Self {
+        match (bits & Self::MASK) >> 2 {
+            0b01 => PayloadEncoding::Unicode,
+            0b10 => PayloadEncoding::Big5,
+            _ => PayloadEncoding::Gbk,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PayloadEncoding::Gbk => "gbk",
+            PayloadEncoding::Unicode => "unicode",
+            PayloadEncoding::Big5 => "big5",
+        }
+    }
+
+    /// Decode scanner output to text, replacing malformed sequences with
+    /// U+FFFD. The flag is `true` if any replacement happened.
+    pub fn decode(self, raw: &[u8]) -> (Cow<'_, str>, bool) {
+        let encoding = match self {
+            PayloadEncoding::Gbk => encoding_rs::GBK,
+            PayloadEncoding::Unicode => encoding_rs::UTF_8,
+            PayloadEncoding::Big5 => encoding_rs::BIG5,
+        };
+        encoding.decode_without_bom_handling(raw)
+    }
+}
+
+impl FromStr for PayloadEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "gbk" => Ok(PayloadEncoding::Gbk),
+            "unicode" => Ok(PayloadEncoding::Unicode),
+            "big5" => Ok(PayloadEncoding::Big5),
+            _ => bail!("Unknown payload encoding: {s}"),
+        }
+    }
+}
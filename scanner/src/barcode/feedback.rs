@@ -0,0 +1,166 @@
+//! Timed beep and LED patterns for scan feedback.
+//!
+//! [`Player`] is stepped from the worker loop with the current time, so a
+//! pattern plays out between UART reads instead of sleeping the worker.
+
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+
+/// Flash count used when none is given.
+const DEFAULT_FLASHES: u8 = 3;
+
+/// A named feedback pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feedback {
+    /// Short chirp with the LED: a good read.
+    Success,
+    /// Two short beeps with the LED: a rejected read.
+    Error,
+    /// One long beep with the LED: something needs attention.
+    Warning,
+    /// Flash the LED this many times without beeping.
+    Flash(u8),
+}
+
+/// Beep and LED state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Outputs {
+    pub beep: bool,
+    pub led: bool,
+}
+
+impl Outputs {
+    pub const OFF: Outputs = Outputs {
+        beep: false,
+        led: false,
+    };
+    const BOTH: Outputs = Outputs {
+        beep: true,
+        led: true,
+    };
+    const LED: Outputs = Outputs {
+        beep: false,
+        led: true,
+    };
+}
+
+/// Outputs held for a duration.
+#[derive(Debug, Clone, Copy)]
+struct Step {
+    outputs: Outputs,
+    duration: Duration,
+}
+
+const fn step(outputs: Outputs, ms: u64) -> Step {
+    Step {
+        outputs,
+        duration: Duration::from_millis(ms),
+    }
+}
+
+impl Feedback {
+    fn steps(self) -> Vec<Step> {
+        match self {
+            Feedback::Success => vec![step(Outputs::BOTH, 80)],
+            Feedback::Error => vec![
+                step(Outputs::BOTH, 100),
+                step(Outputs::OFF, 80),
+                step(Outputs::BOTH, 100),
+            ],
+            Feedback::Warning => vec![step(Outputs::BOTH, 600)],
+            Feedback::Flash(count) => (0..count)
+                .flat_map(|_| [step(Outputs::LED, 150), step(Outputs::OFF, 150)])
+                .collect(),
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Feedback::Success => "success",
+            Feedback::Error => "error",
+            Feedback::Warning => "warning",
+            Feedback::Flash(_) => "flash",
+        }
+    }
+}
+
+impl FromStr for Feedback {
+    type Err = anyhow::Error;
+
+    /// Parse a pattern name; `flash` flashes three times.
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "success" => Ok(Feedback::Success),
+            "error" => Ok(Feedback::Error),
+            "warning" => Ok(Feedback::Warning),
+            "flash" => Ok(Feedback::Flash(DEFAULT_FLASHES)),
+            _ => bail!("Unknown feedback pattern: {s}"),
+        }
+    }
+}
+
+/// Plays one pattern at a time.
+pub struct Player {
+    steps: Vec<Step>,
+    index: usize,
+    step_end: Instant,
+}
+
+impl Default for Player {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Player {
+    pub fn new() -> Self {
+        Self {
+            steps: Vec::new(),
+            index: 0,
+            step_end: Instant::now(),
+        }
+    }
+
+    /// Start `feedback`, replacing any pattern in progress. Returns the
+    /// outputs to apply now.
+    pub fn play(&mut self, feedback: Feedback, now: Instant) -> Outputs {
+        self.steps = feedback.steps();
+        self.index = 0;
+        match self.steps.first() {
+            Some(first) => {
+                self.step_end = now + first.duration;
+                first.outputs
+            }
+            None => Outputs::OFF,
+        }
+    }
+
+    /// Abandon the pattern in progress; the caller sets the outputs.
+    pub fn stop(&mut self) {
+        self.steps.clear();
+        self.index = 0;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.index < self.steps.len()
+    }
+
+    /// Advance the pattern. Returns the outputs to apply when they change;
+    /// both turn off after the last step.
+    pub fn poll(&mut self, now: Instant) -> Option<Outputs> {
+        if !self.is_playing() || now < self.step_end {
+            return None;
+        }
+
+        self.index += 1;
+        match self.steps.get(self.index) {
+            Some(step) => {
+                self.step_end += step.duration;
+                Some(step.outputs)
+            }
+            None => Some(Outputs::OFF),
+        }
+    }
+}
@@ -0,0 +1,389 @@
+//! Splitting the scanner's byte stream into individual scans.
+//!
+//! The GM65 can end each scan with CR, CRLF, TAB or nothing at all (the
+//! factory default), and may wrap it in a configured prefix and suffix.
+//! [`Framer`] is a pure state machine: bytes and the current time go in,
+//! complete frames come out, so it can be driven from recorded streams.
+
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Result};
+
+/// How scan boundaries are recognised.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Framing {
+    /// A scan ends at any of these bytes. Empty scans are skipped, so
+    /// `[CR, LF]` handles CR, LF and CRLF tails alike.
+    Terminator(Vec<u8>),
+    /// A scan ends once no byte has arrived for this long.
+    IdleGap(Duration),
+    /// Every scan is exactly this many bytes.
+    FixedLength(usize),
+    /// A scan is everything between `prefix` and `suffix`; bytes outside
+    /// the envelope are dropped. An empty prefix means each scan starts
+    /// right after the previous suffix.
+    Envelope { prefix: Vec<u8>, suffix: Vec<u8> },
+}
+
+impl Framing {
+    /// Reject strategies under which a scan would never end.
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            Framing::Terminator(terminators) if terminators.is_empty() => {
+                bail!("Terminator framing needs at least one terminator byte")
+            }
+            Framing::FixedLength(0) => bail!("Fixed-length framing needs a length"),
+            Framing::Envelope { suffix, .. } if suffix.is_empty() => {
+                bail!("Envelope framing needs a suffix")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Default for Framing {
+    fn default() -> Self {
+        Framing::Terminator(vec![b'\r', b'\n'])
+    }
+}
+
+/// Output of the framer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameEvent {
+    /// A complete scan, without any terminator or envelope bytes.
+    Frame(Vec<u8>),
+    /// The scan exceeded the buffer limit and was discarded.
+    Overflow,
+}
+
+/// Incremental scan framer.
+pub struct Framer {
+    framing: Framing,
+    max_len: usize,
+    buf: Vec<u8>,
+    last_byte_at: Option<Instant>,
+    /// For envelopes: whether the prefix has been seen.
+    inside: bool,
+    /// For fixed lengths: bytes of the current scan received so far.
+    received: usize,
+    /// Drop bytes until the end of an overflowed scan.
+    discarding: bool,
+}
+
+impl Framer {
+    /// `framing` should have passed [`Framing::validate`].
+    pub fn new(framing: Framing, max_len: usize) -> Self {
+        let mut framer = Self {
+            framing,
+            max_len,
+            buf: Vec::new(),
+            last_byte_at: None,
+            inside: false,
+            received: 0,
+            discarding: false,
+        };
+        framer.reset();
+        framer
+    }
+
+    /// Switch strategy, discarding any partial scan. `framing` should have
+    /// passed [`Framing::validate`].
+    pub fn set_framing(&mut self, framing: Framing) {
+        self.framing = framing;
+        self.reset();
+    }
+
+    /// Change the buffer limit, discarding any partial scan.
+    pub fn set_max_len(&mut self, max_len: usize) {
+        self.max_len = max_len;
+        self.reset();
+    }
+
+    /// Discard any partial scan.
+    pub fn reset(&mut self) {
+        self.buf.clear();
+        self.last_byte_at = None;
+        self.inside = self.starts_inside();
+        self.received = 0;
+        self.discarding = false;
+    }
+
+    /// Whether an envelope scan starts without waiting for a prefix.
+    fn starts_inside(&self) -> bool {
+        matches!(&self.framing, Framing::Envelope { prefix, .. } if prefix.is_empty())
+    }
+
+    /// Whether a partial scan is buffered, i.e. [`Framer::poll`] may fire soon.
+    pub fn is_pending(&self) -> bool {
+        !self.buf.is_empty() || self.discarding
+    }
+
+    /// Feed one received byte.
+    pub fn push(&mut self, byte: u8, now: Instant) -> Option<FrameEvent> {
+        self.last_byte_at = Some(now);
+
+        match &self.framing {
+            Framing::Terminator(terminators) => {
+                if terminators.contains(&byte) {
+                    let discarded = std::mem::take(&mut self.discarding);
+                    return (!discarded).then(|| self.take()).flatten();
+                }
+                self.append(byte)
+            }
+            Framing::IdleGap(_) => self.append(byte),
+            Framing::FixedLength(len) => {
+                let len = *len;
+                // A length over the buffer limit overflows like any other
+                // long scan.
+                let overflow = self.append(byte);
+                self.received += 1;
+                if self.received < len {
+                    return overflow;
+                }
+                self.received = 0;
+                let discarded = std::mem::take(&mut self.discarding);
+                overflow.or_else(|| (!discarded).then(|| self.take()).flatten())
+            }
+            Framing::Envelope { prefix, suffix } => {
+                if !self.inside {
+                    // Keep just enough trailing bytes to recognise the prefix.
+                    self.buf.push(byte);
+                    if self.buf.ends_with(prefix) {
+                        self.buf.clear();
+                        self.inside = true;
+                    } else if self.buf.len() >= prefix.len() {
+                        self.buf.drain(..self.buf.len() + 1 - prefix.len());
+                    }
+                    return None;
+                }
+
+                self.buf.push(byte);
+                if !suffix.is_empty() && self.buf.ends_with(suffix) {
+                    let end = self.buf.len() - suffix.len();
+                    self.buf.truncate(end);
+                    self.inside = self.starts_inside();
+                    if std::mem::take(&mut self.discarding) {
+                        self.buf.clear();
+                        return None;
+                    }
+                    return self.take();
+                }
+                if self.discarding {
+                    // Keep just enough trailing bytes to recognise the suffix.
+                    if self.buf.len() >= suffix.len() {
+                        self.buf.drain(..self.buf.len() + 1 - suffix.len());
+                    }
+                    return None;
+                }
+                if self.buf.len() > self.max_len + suffix.len() {
+                    // Report once, then drop the rest of this scan.
+                    self.buf.clear();
+                    self.discarding = true;
+                    return Some(FrameEvent::Overflow);
+                }
+                None
+            }
+        }
+    }
+
+    /// Check for time-based scan boundaries; call regularly while idle.
+    pub fn poll(&mut self, now: Instant) -> Option<FrameEvent> {
+        let Framing::IdleGap(gap) = self.framing else {
+            return None;
+        };
+        let last = self.last_byte_at?;
+        if now.duration_since(last) < gap {
+            return None;
+        }
+
+        self.last_byte_at = None;
+        if std::mem::take(&mut self.discarding) {
+            self.buf.clear();
+            return None;
+        }
+        self.take()
+    }
+
+    fn append(&mut self, byte: u8) -> Option<FrameEvent> {
+        if self.discarding {
+            return None;
+        }
+        if self.buf.len() >= self.max_len {
+            // Report once, then drop the rest of this scan.
+            self.buf.clear();
+            self.discarding = true;
+            return Some(FrameEvent::Overflow);
+        }
+        self.buf.push(byte);
+        None
+    }
+
+    fn take(&mut self) -> Option<FrameEvent> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        Some(FrameEvent::Frame(std::mem::take(&mut self.buf)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAX_LEN: usize = 16;
+
+    /// Feed `bytes` at one instant and collect what comes out.
+    fn feed(framer: &mut Framer, bytes: &[u8], now: Instant) -> Vec<FrameEvent> {
+        bytes
+            .iter()
+            .filter_map(|&byte| framer.push(byte, now))
+            .collect()
+    }
+
+    fn frame(bytes: &[u8]) -> FrameEvent {
+        FrameEvent::Frame(bytes.to_vec())
+    }
+
+    #[test]
+    fn terminator_splits_cr_lf_and_crlf_tails() {
+        let mut framer = Framer::new(Framing::default(), MAX_LEN);
+        let events = feed(&mut framer, b"ONE\rTWO\nTHREE\r\n", Instant::now());
+        assert_eq!(events, [frame(b"ONE"), frame(b"TWO"), frame(b"THREE")]);
+        assert!(!framer.is_pending());
+    }
+
+    #[test]
+    fn terminator_skips_empty_scans() {
+        let mut framer = Framer::new(Framing::Terminator(vec![b'\t']), MAX_LEN);
+        let events = feed(&mut framer, b"\t\tA\t", Instant::now());
+        assert_eq!(events, [frame(b"A")]);
+    }
+
+    #[test]
+    fn terminator_holds_a_partial_scan() {
+        let mut framer = Framer::new(Framing::default(), MAX_LEN);
+        let now = Instant::now();
+        assert!(feed(&mut framer, b"PART", now).is_empty());
+        assert!(framer.is_pending());
+        assert_eq!(framer.poll(now + Duration::from_secs(60)), None);
+        assert_eq!(feed(&mut framer, b"IAL\r\n", now), [frame(b"PARTIAL")]);
+    }
+
+    #[test]
+    fn idle_gap_ends_a_scan_once_the_line_is_quiet() {
+        let gap = Duration::from_millis(50);
+        let mut framer = Framer::new(Framing::IdleGap(gap), MAX_LEN);
+        let start = Instant::now();
+        assert!(feed(&mut framer, b"AB", start).is_empty());
+        let later = start + Duration::from_millis(30);
+        assert!(feed(&mut framer, b"C", later).is_empty());
+
+        // The gap counts from the last byte, not the first.
+        assert_eq!(framer.poll(start + gap), None);
+        assert_eq!(framer.poll(later + gap), Some(frame(b"ABC")));
+        assert_eq!(framer.poll(later + gap * 2), None);
+        assert!(!framer.is_pending());
+    }
+
+    #[test]
+    fn fixed_length_cuts_every_n_bytes() {
+        let mut framer = Framer::new(Framing::FixedLength(4), MAX_LEN);
+        let events = feed(&mut framer, b"12345678\r\n", Instant::now());
+        assert_eq!(events, [frame(b"1234"), frame(b"5678")]);
+        assert!(framer.is_pending());
+    }
+
+    #[test]
+    fn envelope_drops_bytes_outside_prefix_and_suffix() {
+        let framing = Framing::Envelope {
+            prefix: b"<<".to_vec(),
+            suffix: b">>".to_vec(),
+        };
+        let mut framer = Framer::new(framing, MAX_LEN);
+        let events = feed(&mut framer, b"noise<<ONE>>\r\n<<<TWO>>", Instant::now());
+        assert_eq!(events, [frame(b"ONE"), frame(b"<TWO")]);
+    }
+
+    #[test]
+    fn envelope_reports_an_overlong_scan() {
+        let framing = Framing::Envelope {
+            prefix: b"[".to_vec(),
+            suffix: b"]".to_vec(),
+        };
+        let mut framer = Framer::new(framing, 4);
+        let events = feed(&mut framer, b"[TOO-LONG][OK]", Instant::now());
+        assert_eq!(events, [FrameEvent::Overflow, frame(b"OK")]);
+    }
+
+    #[test]
+    fn envelope_without_a_prefix_keeps_every_byte() {
+        let framing = Framing::Envelope {
+            prefix: Vec::new(),
+            suffix: b"\r\n".to_vec(),
+        };
+        let mut framer = Framer::new(framing, MAX_LEN);
+        let events = feed(&mut framer, b"ONE\r\nTWO\r\n", Instant::now());
+        assert_eq!(events, [frame(b"ONE"), frame(b"TWO")]);
+
+        // Also after an overflow, which drops the rest of the scan.
+        framer.set_max_len(2);
+        let events = feed(&mut framer, b"TOO-LONG\r\nOK\r\n", Instant::now());
+        assert_eq!(events, [FrameEvent::Overflow, frame(b"OK")]);
+    }
+
+    #[test]
+    fn rejects_framing_that_never_ends_a_scan() {
+        let no_suffix = Framing::Envelope {
+            prefix: b"[".to_vec(),
+            suffix: Vec::new(),
+        };
+        assert!(no_suffix.validate().is_err());
+        assert!(Framing::Terminator(Vec::new()).validate().is_err());
+        assert!(Framing::FixedLength(0).validate().is_err());
+        assert!(Framing::default().validate().is_ok());
+        assert!(Framing::FixedLength(4).validate().is_ok());
+    }
+
+    #[test]
+    fn fixed_length_over_the_limit_overflows() {
+        let mut framer = Framer::new(Framing::FixedLength(6), 4);
+        let events = feed(&mut framer, b"123456ABCDEF", Instant::now());
+        assert_eq!(events, [FrameEvent::Overflow, FrameEvent::Overflow]);
+        assert!(!framer.is_pending());
+
+        framer.set_framing(Framing::FixedLength(4));
+        assert_eq!(feed(&mut framer, b"1234", Instant::now()), [frame(b"1234")]);
+    }
+
+    #[test]
+    fn overflow_discards_the_rest_of_the_scan() {
+        let mut framer = Framer::new(Framing::default(), 4);
+        let events = feed(&mut framer, b"TOO-LONG\r\nOK\r\n", Instant::now());
+        assert_eq!(events, [FrameEvent::Overflow, frame(b"OK")]);
+    }
+
+    #[test]
+    fn idle_gap_overflow_discards_until_the_gap() {
+        let gap = Duration::from_millis(50);
+        let mut framer = Framer::new(Framing::IdleGap(gap), 4);
+        let now = Instant::now();
+        assert_eq!(feed(&mut framer, b"TOO-LONG", now), [FrameEvent::Overflow]);
+        assert!(framer.is_pending());
+        assert_eq!(framer.poll(now + gap), None);
+        assert!(!framer.is_pending());
+
+        let later = now + gap * 2;
+        assert!(feed(&mut framer, b"OK", later).is_empty());
+        assert_eq!(framer.poll(later + gap), Some(frame(b"OK")));
+    }
+
+    #[test]
+    fn changing_framing_drops_a_partial_scan() {
+        let mut framer = Framer::new(Framing::default(), MAX_LEN);
+        let now = Instant::now();
+        feed(&mut framer, b"STALE", now);
+        framer.set_framing(Framing::FixedLength(2));
+        assert!(!framer.is_pending());
+        assert_eq!(feed(&mut framer, b"AB", now), [frame(b"AB")]);
+    }
+}
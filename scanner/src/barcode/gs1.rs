@@ -0,0 +1,576 @@
+//! GS1 Application Identifier (AI) parsing for GS1-128, GS1 DataMatrix,
+//! GS1 QR and GS1 DataBar payloads.
+//!
+//! Accepts both the transmitted form, where FNC1 separators arrive as the
+//! ASCII GS character (`0x1D`) and the payload may start with a symbology
+//! identifier such as `]C1`, and the human readable `(01)...(17)...` form.
+
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Group separator; the GM65 transmits FNC1 field separators as this byte.
+pub const GS: char = '\u{1D}';
+
+/// Symbology identifiers that mark a payload as GS1 data.
+const GS1_IDENTIFIERS: [&str; 4] = ["]C1", "]e0", "]d2", "]Q3"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DataLen {
+    /// Exactly this many characters; no separator needed.
+    Fixed(usize),
+    /// Up to this many characters, terminated by GS or end of data.
+    Variable(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DataKind {
+    Text,
+    /// `YYMMDD`, where `DD` may be `00` for "end of month".
+    Date,
+}
+
+struct AiSpec {
+    /// Leading digits matched against the AI; shorter than `ai_len` for
+    /// families like `310n` where the last digit is a parameter.
+    prefix: &'static str,
+    ai_len: usize,
+    data: DataLen,
+    kind: DataKind,
+    title: &'static str,
+}
+
+const fn ai(prefix: &'static str, ai_len: usize, data: DataLen, title: &'static str) -> AiSpec {
+    AiSpec {
+        prefix,
+        ai_len,
+        data,
+        kind: DataKind::Text,
+        title,
+    }
+}
+
+const fn date(prefix: &'static str, ai_len: usize, title: &'static str) -> AiSpec {
+    AiSpec {
+        prefix,
+        ai_len,
+        data: DataLen::Fixed(6),
+        kind: DataKind::Date,
+        title,
+    }
+}
+
+use DataLen::{Fixed, Variable};
+
+/// Commonly used AIs from the GS1 General Specifications, section 3.2.
+const AI_TABLE: &[AiSpec] = &[
+    ai("00", 2, Fixed(18), "SSCC"),
+    ai("01", 2, Fixed(14), "GTIN"),
+    ai("02", 2, Fixed(14), "CONTENT"),
+    ai("10", 2, Variable(20), "BATCH/LOT"),
+    date("11", 2, "PROD DATE"),
+    date("12", 2, "DUE DATE"),
+    date("13", 2, "PACK DATE"),
+    date("15", 2, "BEST BEFORE"),
+    date("16", 2, "SELL BY"),
+    date("17", 2, "USE BY"),
+    ai("20", 2, Fixed(2), "VARIANT"),
+    ai("21", 2, Variable(20), "SERIAL"),
+    ai("22", 2, Variable(20), "CPV"),
+    ai("235", 3, Variable(28), "TPX"),
+    ai("240", 3, Variable(30), "ADDITIONAL ID"),
+    ai("241", 3, Variable(30), "CUST. PART No."),
+    ai("242", 3, Variable(6), "MTO VARIANT"),
+    ai("243", 3, Variable(20), "PCN"),
+    ai("250", 3, Variable(30), "SECONDARY SERIAL"),
+    ai("251", 3, Variable(30), "REF. TO SOURCE"),
+    ai("253", 3, Variable(30), "GDTI"),
+    ai("254", 3, Variable(20), "GLN EXTENSION"),
+    ai("255", 3, Variable(25), "GCN"),
+    ai("30", 2, Variable(8), "VAR. COUNT"),
+    ai("310", 4, Fixed(6), "NET WEIGHT (kg)"),
+    ai("311", 4, Fixed(6), "LENGTH (m)"),
+    ai("312", 4, Fixed(6), "WIDTH (m)"),
+    ai("313", 4, Fixed(6), "HEIGHT (m)"),
+    ai("314", 4, Fixed(6), "AREA (m2)"),
+    ai("315", 4, Fixed(6), "NET VOLUME (l)"),
+    ai("316", 4, Fixed(6), "NET VOLUME (m3)"),
+    ai("320", 4, Fixed(6), "NET WEIGHT (lb)"),
+    ai("330", 4, Fixed(6), "GROSS WEIGHT (kg)"),
+    // Remaining 31xx-36xx trade measures share the same layout.
+    ai("31", 4, Fixed(6), "MEASURE"),
+    ai("32", 4, Fixed(6), "MEASURE"),
+    ai("33", 4, Fixed(6), "MEASURE"),
+    ai("34", 4, Fixed(6), "MEASURE"),
+    ai("35", 4, Fixed(6), "MEASURE"),
+    ai("36", 4, Fixed(6), "MEASURE"),
+    ai("37", 2, Variable(8), "COUNT"),
+    ai("390", 4, Variable(15), "AMOUNT"),
+    ai("391", 4, Variable(18), "AMOUNT"),
+    ai("392", 4, Variable(15), "PRICE"),
+    ai("393", 4, Variable(18), "PRICE"),
+    ai("400", 3, Variable(30), "ORDER NUMBER"),
+    ai("401", 3, Variable(30), "GINC"),
+    ai("402", 3, Fixed(17), "GSIN"),
+    ai("403", 3, Variable(30), "ROUTE"),
+    ai("410", 3, Fixed(13), "SHIP TO LOC"),
+    ai("411", 3, Fixed(13), "BILL TO"),
+    ai("412", 3, Fixed(13), "PURCHASE FROM"),
+    ai("413", 3, Fixed(13), "SHIP FOR LOC"),
+    ai("414", 3, Fixed(13), "LOC No."),
+    ai("415", 3, Fixed(13), "PAY TO"),
+    ai("416", 3, Fixed(13), "PROD/SERV LOC"),
+    ai("417", 3, Fixed(13), "PARTY"),
+    ai("420", 3, Variable(20), "SHIP TO POST"),
+    ai("421", 3, Variable(12), "SHIP TO POST"),
+    ai("422", 3, Fixed(3), "ORIGIN"),
+    ai("423", 3, Variable(15), "COUNTRY - INITIAL PROCESS"),
+    ai("424", 3, Fixed(3), "COUNTRY - PROCESS"),
+    ai("425", 3, Variable(15), "COUNTRY - DISASSEMBLY"),
+    ai("426", 3, Fixed(3), "COUNTRY - FULL PROCESS"),
+    ai("427", 3, Variable(3), "ORIGIN SUBDIVISION"),
+    ai("7003", 4, Fixed(10), "EXPIRY TIME"),
+    date("7006", 4, "FIRST FREEZE DATE"),
+    ai("71", 3, Variable(20), "NHRN"),
+    ai("8003", 4, Variable(30), "GRAI"),
+    ai("8004", 4, Variable(30), "GIAI"),
+    ai("8005", 4, Fixed(6), "PRICE PER UNIT"),
+    ai("8006", 4, Fixed(18), "ITIP"),
+    ai("8007", 4, Variable(34), "IBAN"),
+    ai("8008", 4, Variable(12), "PROD TIME"),
+    ai("8017", 4, Fixed(18), "GSRN - PROVIDER"),
+    ai("8018", 4, Fixed(18), "GSRN - RECIPIENT"),
+    ai("8020", 4, Variable(25), "REF No."),
+    ai("8200", 4, Variable(70), "PRODUCT URL"),
+    ai("90", 2, Variable(30), "INTERNAL"),
+    ai("91", 2, Variable(90), "INTERNAL"),
+    ai("92", 2, Variable(90), "INTERNAL"),
+    ai("93", 2, Variable(90), "INTERNAL"),
+    ai("94", 2, Variable(90), "INTERNAL"),
+    ai("95", 2, Variable(90), "INTERNAL"),
+    ai("96", 2, Variable(90), "INTERNAL"),
+    ai("97", 2, Variable(90), "INTERNAL"),
+    ai("98", 2, Variable(90), "INTERNAL"),
+    ai("99", 2, Variable(90), "INTERNAL"),
+];
+
+fn lookup(data: &str) -> Option<&'static AiSpec> {
+    AI_TABLE.iter().find(|spec| {
+        data.len() >= spec.ai_len
+            && data.starts_with(spec.prefix)
+            && data.as_bytes()[..spec.ai_len]
+                .iter()
+                .all(u8::is_ascii_digit)
+    })
+}
+
+/// A GS1 date field. `day` is `0` when the label only gives year and month.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Gs1Date {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+}
+
+impl Gs1Date {
+    /// Parse `YYMMDD`. The century follows the GS1 sliding window (General
+    /// Specifications, section 7.12): the year falls within 49 years before
+    /// and 50 years after `this_year`. Without a current year, years are
+    /// taken as 2000-2099.
+    fn parse(value: &str, this_year: Option<u16>) -> Option<Self> {
+        if value.len() != 6 || !value.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let yy = value[0..2].parse::<u16>().ok()?;
+        let year = this_year.map_or(2000 + yy, |this_year| expand_year(yy, this_year));
+        let month = value[2..4].parse::<u8>().ok()?;
+        let day = value[4..6].parse::<u8>().ok()?;
+        if !(1..=12).contains(&month) || day > 31 {
+            return None;
+        }
+        Some(Gs1Date { year, month, day })
+    }
+}
+
+/// Four-digit year for `yy` in the window around `this_year`.
+fn expand_year(yy: u16, this_year: u16) -> u16 {
+    let century = this_year - this_year % 100;
+    match i32::from(yy) - i32::from(this_year % 100) {
+        51.. => century - 100 + yy,
+        ..=-50 => century + 100 + yy,
+        _ => century + yy,
+    }
+}
+
+/// UTC year of `time`, for [`parse`]; `None` while the clock is unset, i.e.
+/// still counting from 1970.
+pub fn year_of(time: SystemTime) -> Option<u16> {
+    let mut days = time.duration_since(UNIX_EPOCH).ok()?.as_secs() / 86_400;
+    let mut year = 1970;
+    loop {
+        let leap = (year % 4 == 0 && year % 100 != 0) || year % 400 == 0;
+        let len = if leap { 366 } else { 365 };
+        if days < len {
+            break;
+        }
+        days -= len;
+        year += 1;
+    }
+    (year >= 2000).then_some(year)
+}
+
+impl fmt::Display for Gs1Date {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.day == 0 {
+            write!(f, "{:04}-{:02}", self.year, self.month)
+        } else {
+            write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+        }
+    }
+}
+
+/// One AI and its data field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gs1Element {
+    pub ai: String,
+    pub value: String,
+    /// Parsed value for date AIs.
+    pub date: Option<Gs1Date>,
+}
+
+impl Gs1Element {
+    /// Data title from the GS1 General Specifications, e.g. `"BATCH/LOT"`.
+    pub fn title(&self) -> &'static str {
+        lookup(&self.ai).map_or("UNKNOWN", |spec| spec.title)
+    }
+}
+
+/// A payload split into its AI elements, in label order.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Gs1Message {
+    pub elements: Vec<Gs1Element>,
+}
+
+impl Gs1Message {
+    /// Data field for `ai`, if present.
+    pub fn get(&self, ai: &str) -> Option<&Gs1Element> {
+        self.elements.iter().find(|e| e.ai == ai)
+    }
+
+    /// GTIN of the trade item (AI 01), or of contained items (AI 02).
+    pub fn gtin(&self) -> Option<&str> {
+        self.get("01")
+            .or_else(|| self.get("02"))
+            .map(|e| e.value.as_str())
+    }
+
+    /// Batch or lot number (AI 10).
+    pub fn batch(&self) -> Option<&str> {
+        self.get("10").map(|e| e.value.as_str())
+    }
+
+    /// Serial number (AI 21).
+    pub fn serial(&self) -> Option<&str> {
+        self.get("21").map(|e| e.value.as_str())
+    }
+
+    /// Expiration date (AI 17).
+    pub fn expiry(&self) -> Option<Gs1Date> {
+        self.get("17").and_then(|e| e.date)
+    }
+
+    /// Best before date (AI 15).
+    pub fn best_before(&self) -> Option<Gs1Date> {
+        self.get("15").and_then(|e| e.date)
+    }
+}
+
+/// Reasons a payload could not be parsed as GS1 data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Gs1Error {
+    /// Payload contains characters outside the GS1 character sets.
+    InvalidCharacter,
+    /// No known AI at this position.
+    UnknownAi(String),
+    /// Fixed-length field ended early.
+    Truncated(String),
+    /// Variable-length field longer than its maximum.
+    TooLong(String),
+    /// Date field is not a valid `YYMMDD`.
+    InvalidDate(String),
+    /// Payload contains no elements.
+    Empty,
+}
+
+impl fmt::Display for Gs1Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Gs1Error::InvalidCharacter => write!(f, "invalid character in GS1 data"),
+            Gs1Error::UnknownAi(at) => write!(f, "unknown AI at {at:?}"),
+            Gs1Error::Truncated(ai) => write!(f, "AI ({ai}) data too short"),
+            Gs1Error::TooLong(ai) => write!(f, "AI ({ai}) data too long"),
+            Gs1Error::InvalidDate(ai) => write!(f, "AI ({ai}) has an invalid date"),
+            Gs1Error::Empty => write!(f, "no GS1 elements"),
+        }
+    }
+}
+
+impl std::error::Error for Gs1Error {}
+
+/// Whether the payload is explicitly marked as GS1 data: a GS1 symbology
+/// identifier, a leading FNC1, or the bracketed `(AI)` notation.
+pub fn has_marker(payload: &str) -> bool {
+    GS1_IDENTIFIERS.iter().any(|id| payload.starts_with(id))
+        || payload.starts_with(GS)
+        || (payload.starts_with('(') && payload[1..].find(')').is_some_and(|end| end >= 2))
+}
+
+/// Split a payload into AI elements. Two-digit years in date fields are
+/// placed around `this_year`, see [`year_of`].
+pub fn parse(payload: &str, this_year: Option<u16>) -> Result<Gs1Message, Gs1Error> {
+    if !payload.is_ascii() {
+        return Err(Gs1Error::InvalidCharacter);
+    }
+
+    let data = GS1_IDENTIFIERS
+        .iter()
+        .find_map(|id| payload.strip_prefix(id))
+        .unwrap_or(payload);
+
+    let elements = if data.starts_with('(') {
+        parse_bracketed(data, this_year)?
+    } else {
+        parse_transmitted(data, this_year)?
+    };
+
+    if elements.is_empty() {
+        return Err(Gs1Error::Empty);
+    }
+    Ok(Gs1Message { elements })
+}
+
+/// Parse `AI data [GS] AI data ...`.
+fn parse_transmitted(mut rest: &str, this_year: Option<u16>) -> Result<Vec<Gs1Element>, Gs1Error> {
+    let mut elements = Vec::new();
+
+    loop {
+        rest = rest.trim_start_matches(GS);
+        if rest.is_empty() {
+            break;
+        }
+
+        let spec = lookup(rest).ok_or_else(|| Gs1Error::UnknownAi(truncate(rest, 4)))?;
+        let ai = &rest[..spec.ai_len];
+        rest = &rest[spec.ai_len..];
+
+        let value = match spec.data {
+            DataLen::Fixed(len) => {
+                if rest.len() < len || rest[..len].contains(GS) {
+                    return Err(Gs1Error::Truncated(ai.to_string()));
+                }
+                &rest[..len]
+            }
+            DataLen::Variable(max) => {
+                let end = rest.find(GS).unwrap_or(rest.len());
+                if end > max {
+                    return Err(Gs1Error::TooLong(ai.to_string()));
+                }
+                &rest[..end]
+            }
+        };
+        rest = &rest[value.len()..];
+
+        elements.push(element(spec, ai, value, this_year)?);
+    }
+
+    Ok(elements)
+}
+
+/// Parse the human readable `(AI)data(AI)data` form.
+fn parse_bracketed(data: &str, this_year: Option<u16>) -> Result<Vec<Gs1Element>, Gs1Error> {
+    let mut elements = Vec::new();
+
+    for part in data.split('(').skip(1) {
+        let (ai, value) = part
+            .split_once(')')
+            .ok_or_else(|| Gs1Error::UnknownAi(truncate(part, 4)))?;
+
+        let spec = lookup(ai)
+            .filter(|spec| spec.ai_len == ai.len())
+            .ok_or_else(|| Gs1Error::UnknownAi(ai.to_string()))?;
+
+        match spec.data {
+            DataLen::Fixed(len) if value.len() != len => {
+                return Err(Gs1Error::Truncated(ai.to_string()))
+            }
+            DataLen::Variable(max) if value.len() > max => {
+                return Err(Gs1Error::TooLong(ai.to_string()))
+            }
+            _ => {}
+        }
+
+        elements.push(element(spec, ai, value, this_year)?);
+    }
+
+    Ok(elements)
+}
+
+fn element(
+    spec: &AiSpec,
+    ai: &str,
+    value: &str,
+    this_year: Option<u16>,
+) -> Result<Gs1Element, Gs1Error> {
+    let date = match spec.kind {
+        DataKind::Date => Some(
+            Gs1Date::parse(value, this_year)
+                .ok_or_else(|| Gs1Error::InvalidDate(ai.to_string()))?,
+        ),
+        DataKind::Text => None,
+    };
+
+    Ok(Gs1Element {
+        ai: ai.to_string(),
+        value: value.to_string(),
+        date,
+    })
+}
+
+fn truncate(s: &str, max: usize) -> String {
+    s.chars().take(max).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    const THIS_YEAR: Option<u16> = Some(2026);
+
+    fn parse_now(payload: &str) -> Result<Gs1Message, Gs1Error> {
+        parse(payload, THIS_YEAR)
+    }
+
+    #[test]
+    fn reads_transmitted_and_bracketed_forms_alike() {
+        let transmitted = parse_now("]C10109501101020917171905081012AB\u{1D}21XYZ").unwrap();
+        let bracketed = parse_now("(01)09501101020917(17)190508(10)12AB(21)XYZ").unwrap();
+        assert_eq!(transmitted, bracketed);
+
+        assert_eq!(transmitted.gtin(), Some("09501101020917"));
+        assert_eq!(transmitted.batch(), Some("12AB"));
+        assert_eq!(transmitted.serial(), Some("XYZ"));
+        let expiry = transmitted.expiry().unwrap();
+        assert_eq!(expiry.to_string(), "2019-05-08");
+        assert_eq!(transmitted.get("10").unwrap().title(), "BATCH/LOT");
+    }
+
+    #[test]
+    fn variable_length_data_ends_at_gs_or_end_of_input() {
+        let message = parse_now("\u{1D}10LOT-1\u{1D}3712\u{1D}\u{1D}21S/N").unwrap();
+        let values: Vec<_> = message.elements.iter().map(|e| e.value.as_str()).collect();
+        assert_eq!(values, ["LOT-1", "12", "S/N"]);
+
+        // Fixed-length data needs no separator after it.
+        let message = parse_now("0109501101020917").unwrap();
+        assert_eq!(message.elements.len(), 1);
+        let message = parse_now("010950110102091710LOT").unwrap();
+        assert_eq!(message.batch(), Some("LOT"));
+
+        let long = format!("10{}", "X".repeat(21));
+        assert_eq!(parse_now(&long), Err(Gs1Error::TooLong("10".into())));
+        assert_eq!(
+            parse_now(&format!("({long}")),
+            Err(Gs1Error::UnknownAi(long[..4].into()))
+        );
+        assert_eq!(
+            parse_now(&format!("(10){}", "X".repeat(21))),
+            Err(Gs1Error::TooLong("10".into()))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_ais() {
+        assert_eq!(
+            parse_now("0412345"),
+            Err(Gs1Error::UnknownAi("0412".into()))
+        );
+        assert_eq!(
+            parse_now("(04)12345"),
+            Err(Gs1Error::UnknownAi("04".into()))
+        );
+        // The AI must be written out in full in the bracketed form.
+        assert_eq!(
+            parse_now("(310)000500"),
+            Err(Gs1Error::UnknownAi("310".into()))
+        );
+        assert_eq!(parse_now("]C1"), Err(Gs1Error::Empty));
+        assert_eq!(parse_now("10LOT\u{e9}"), Err(Gs1Error::InvalidCharacter));
+    }
+
+    #[test]
+    fn rejects_truncated_fixed_length_data() {
+        assert_eq!(parse_now("01123"), Err(Gs1Error::Truncated("01".into())));
+        assert_eq!(
+            parse_now("01095011\u{1D}10LOT"),
+            Err(Gs1Error::Truncated("01".into()))
+        );
+        assert_eq!(parse_now("(01)123"), Err(Gs1Error::Truncated("01".into())));
+        assert_eq!(
+            parse_now("3103000"),
+            Err(Gs1Error::Truncated("3103".into()))
+        );
+    }
+
+    #[test]
+    fn detects_gs1_markers() {
+        for payload in [
+            "]C10109501101020917",
+            "]e00109501101020917",
+            "]d20109501101020917",
+            "]Q30109501101020917",
+            "\u{1D}0109501101020917",
+            "(01)09501101020917",
+        ] {
+            assert!(has_marker(payload), "{payload:?}");
+            assert_eq!(parse_now(payload).unwrap().gtin(), Some("09501101020917"));
+        }
+        for payload in ["0109501101020917", "]C00109501101020917", "(1)2", "()"] {
+            assert!(!has_marker(payload), "{payload:?}");
+        }
+    }
+
+    #[test]
+    fn dates_follow_the_sliding_window() {
+        let year = |value: &str, this_year| Gs1Date::parse(value, Some(this_year)).unwrap().year;
+        assert_eq!(year("760101", 2026), 2076);
+        assert_eq!(year("770101", 2026), 1977);
+        assert_eq!(year("990101", 2026), 1999);
+        assert_eq!(year("000101", 2026), 2000);
+        assert_eq!(year("290101", 2080), 2129);
+        assert_eq!(year("300101", 2080), 2130);
+        assert_eq!(year("310101", 2080), 2031);
+        assert_eq!(Gs1Date::parse("770101", None).unwrap().year, 2077);
+
+        let date = Gs1Date::parse("261000", THIS_YEAR).unwrap();
+        assert_eq!(date.to_string(), "2026-10");
+        assert_eq!(
+            parse_now("17261300"),
+            Err(Gs1Error::InvalidDate("17".into()))
+        );
+        assert_eq!(
+            parse_now("17261032"),
+            Err(Gs1Error::InvalidDate("17".into()))
+        );
+    }
+
+    #[test]
+    fn finds_the_year_of_a_timestamp() {
+        let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+        assert_eq!(year_of(at(951_825_600)), Some(2000));
+        assert_eq!(year_of(at(1_735_689_599)), Some(2024));
+        assert_eq!(year_of(at(1_735_689_600)), Some(2025));
+        // A clock that was never set.
+        assert_eq!(year_of(at(3_600)), None);
+    }
+}
@@ -0,0 +1,294 @@
+//! A simulated GM65 for running the barcode worker off-target.
+//!
+//! [`MockScanner`] answers zone-bit commands from an in-memory zone table
+//! and delivers recorded byte streams as scans. It only talks while the port
+//! and the simulated scanner agree on the baud rate, so baud detection and
+//! switching behave as on hardware. [`MockPin`] records the level of a
+//! control pin.
+
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use embedded_hal::digital::{ErrorType as PinErrorType, OutputPin};
+use embedded_io::{ErrorType, Write};
+
+use super::port::{ScannerPort, ScannerRx};
+use super::protocol::{
+    crc_ccitt, zone, COMMAND_HEAD, RESPONSE_HEAD, TYPE_READ_ZONE, TYPE_SAVE_TO_FLASH,
+    TYPE_WRITE_ZONE,
+};
+use super::BaudRate;
+
+/// Zone bits the simulated scanner holds; enough for every documented zone.
+const ZONE_SIZE: usize = 0x100;
+
+struct State {
+    /// Bytes sent by the scanner, not yet read by the port.
+    rx: VecDeque<u8>,
+    /// Command bytes received by the scanner, not yet parsed.
+    commands: Vec<u8>,
+    zones: [u8; ZONE_SIZE],
+    /// Zones saved with the save-to-flash command, restored at power-up.
+    saved: [u8; ZONE_SIZE],
+    port_bps: u32,
+    /// Byte ending a receive burst, as set by the worker.
+    terminator: Option<u8>,
+    powered: bool,
+}
+
+impl State {
+    fn scanner_bps(&self) -> Option<u32> {
+        let address = zone::BAUD_RATE as usize;
+        BaudRate::from_zone_bytes([self.zones[address], self.zones[address + 1]]).map(BaudRate::bps)
+    }
+
+    /// Whether bytes get through in both directions.
+    fn linked(&self) -> bool {
+        self.powered && self.scanner_bps() == Some(self.port_bps)
+    }
+
+    /// Answer every complete command received so far.
+    fn process_commands(&mut self) {
+        loop {
+            let Some(start) = self
+                .commands
+                .windows(2)
+                .position(|head| head == COMMAND_HEAD)
+            else {
+                self.commands.clear();
+                return;
+            };
+            self.commands.drain(..start);
+
+            let [_, _, ty, lens, addr_high, addr_low, ..] = self.commands[..] else {
+                return;
+            };
+            let data_len = if ty == TYPE_WRITE_ZONE {
+                lens as usize
+            } else {
+                1
+            };
+            let frame_len = COMMAND_HEAD.len() + 4 + data_len + 2;
+            if self.commands.len() < frame_len {
+                return;
+            }
+            let frame: Vec<u8> = self.commands.drain(..frame_len).collect();
+
+            let body = &frame[COMMAND_HEAD.len()..frame_len - 2];
+            let crc = &frame[frame_len - 2..];
+            // The GM65 accepts `AB CD` in place of a real CRC.
+            if crc != crc_ccitt(body).to_be_bytes() && crc != [0xAB, 0xCD] {
+                continue;
+            }
+
+            let address = u16::from_be_bytes([addr_high, addr_low]) as usize;
+            let data = &frame[6..6 + data_len];
+            match ty {
+                TYPE_READ_ZONE => {
+                    let len = if data[0] == 0 { 256 } else { data[0] as usize };
+                    let end = (address + len).min(ZONE_SIZE);
+                    let reply = self.zones[address.min(end)..end].to_vec();
+                    self.reply(&reply);
+                }
+                TYPE_WRITE_ZONE => {
+                    // Reply first: a new baud rate applies after the reply.
+                    self.reply(&[0x00]);
+                    for (i, &byte) in data.iter().enumerate() {
+                        if let Some(zone) = self.zones.get_mut(address + i) {
+                            *zone = byte;
+                        }
+                    }
+                }
+                TYPE_SAVE_TO_FLASH => {
+                    self.saved = self.zones;
+                    self.reply(&[0x00]);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn reply(&mut self, data: &[u8]) {
+        let mut covered = vec![0x00, data.len() as u8];
+        covered.extend_from_slice(data);
+        let crc = crc_ccitt(&covered);
+
+        self.rx.extend(RESPONSE_HEAD);
+        self.rx.extend(covered);
+        self.rx.extend(crc.to_be_bytes());
+    }
+}
+
+/// A simulated scanner attached to a port; clones share the scanner, so a
+/// test can keep one to feed scans while the worker owns the other.
+#[derive(Clone)]
+pub struct MockScanner {
+    shared: Arc<(Mutex<State>, Condvar)>,
+}
+
+impl Default for MockScanner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MockScanner {
+    /// A powered scanner at factory defaults (9600 baud) on a 9600 baud port.
+    pub fn new() -> Self {
+        let mut zones = [0u8; ZONE_SIZE];
+        let address = zone::BAUD_RATE as usize;
+        zones[address..address + 2].copy_from_slice(&BaudRate::B9600.zone_bytes());
+
+        let state = State {
+            rx: VecDeque::new(),
+            commands: Vec::new(),
+            zones,
+            saved: zones,
+            port_bps: BaudRate::B9600.bps(),
+            terminator: None,
+            powered: true,
+        };
+        Self {
+            shared: Arc::new((Mutex::new(state), Condvar::new())),
+        }
+    }
+
+    /// Send a recorded byte stream, as if the scanner had read a label.
+    pub fn replay(&self, bytes: &[u8]) {
+        let (state, ready) = &*self.shared;
+        let mut state = state.lock().unwrap();
+        if state.linked() {
+            state.rx.extend(bytes);
+            ready.notify_all();
+        }
+    }
+
+    /// Cut or restore power. A scanner powering up is back at its saved
+    /// settings.
+    pub fn set_powered(&self, powered: bool) {
+        let mut state = self.shared.0.lock().unwrap();
+        if powered && !state.powered {
+            state.zones = state.saved;
+        }
+        state.powered = powered;
+        state.rx.clear();
+        state.commands.clear();
+    }
+
+    pub fn zone(&self, address: u16) -> u8 {
+        self.shared.0.lock().unwrap().zones[address as usize]
+    }
+
+    pub fn set_zone(&self, address: u16, value: u8) {
+        self.shared.0.lock().unwrap().zones[address as usize] = value;
+    }
+}
+
+impl ErrorType for MockScanner {
+    type Error = Infallible;
+}
+
+impl Write for MockScanner {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Infallible> {
+        let (state, ready) = &*self.shared;
+        let mut state = state.lock().unwrap();
+        if state.linked() {
+            state.commands.extend_from_slice(buf);
+            state.process_commands();
+            ready.notify_all();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+impl ScannerPort for MockScanner {
+    type Rx = MockScanner;
+
+    fn take_rx(&mut self) -> Result<MockScanner> {
+        Ok(self.clone())
+    }
+
+    fn baud_rate(&self) -> Result<u32> {
+        Ok(self.shared.0.lock().unwrap().port_bps)
+    }
+
+    fn set_baud_rate(&mut self, bps: u32) -> Result<()> {
+        self.shared.0.lock().unwrap().port_bps = bps;
+        Ok(())
+    }
+
+    fn clear_rx(&mut self) -> Result<()> {
+        self.shared.0.lock().unwrap().rx.clear();
+        Ok(())
+    }
+
+    fn set_terminator(&mut self, byte: Option<u8>) -> Result<()> {
+        self.shared.0.lock().unwrap().terminator = byte;
+        Ok(())
+    }
+}
+
+impl ScannerRx for MockScanner {
+    fn read_burst(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize> {
+        let deadline = Instant::now() + timeout;
+        let (state, ready) = &*self.shared;
+        let mut state = state.lock().unwrap();
+        while state.rx.is_empty() {
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(0);
+            }
+            state = ready.wait_timeout(state, deadline - now).unwrap().0;
+        }
+
+        let burst = match state.terminator {
+            Some(terminator) => state
+                .rx
+                .iter()
+                .position(|&byte| byte == terminator)
+                .map_or(state.rx.len(), |end| end + 1),
+            None => state.rx.len(),
+        };
+        let len = buf.len().min(burst);
+        for (slot, byte) in buf.iter_mut().zip(state.rx.drain(..len)) {
+            *slot = byte;
+        }
+        Ok(len)
+    }
+}
+
+/// An output pin that remembers its level; clones share it.
+#[derive(Clone, Default)]
+pub struct MockPin {
+    high: Arc<AtomicBool>,
+}
+
+impl MockPin {
+    pub fn is_high(&self) -> bool {
+        self.high.load(Ordering::Relaxed)
+    }
+}
+
+impl PinErrorType for MockPin {
+    type Error = Infallible;
+}
+
+impl OutputPin for MockPin {
+    fn set_low(&mut self) -> Result<(), Infallible> {
+        self.high.store(false, Ordering::Relaxed);
+        Ok(())
+    }
+
+    fn set_high(&mut self) -> Result<(), Infallible> {
+        self.high.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
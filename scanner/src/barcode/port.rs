@@ -0,0 +1,39 @@
+//! The serial link the barcode worker talks to the scanner over.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use embedded_io::Write;
+
+/// A byte stream to a scanner.
+///
+/// The worker writes commands through the port itself and receives on a
+/// separate thread through the half returned by [`ScannerPort::take_rx`], so
+/// it can wait for scanner data and control messages at the same time.
+pub trait ScannerPort: Write {
+    type Rx: ScannerRx;
+
+    /// Split off the receive side. Called once, before the worker starts.
+    fn take_rx(&mut self) -> Result<Self::Rx>;
+
+    /// Current line rate in bits per second.
+    fn baud_rate(&self) -> Result<u32>;
+
+    /// Change the line rate.
+    fn set_baud_rate(&mut self, bps: u32) -> Result<()>;
+
+    /// Discard bytes received but not yet read.
+    fn clear_rx(&mut self) -> Result<()>;
+
+    /// End receive bursts at `byte`, so a terminated scan is read in one
+    /// go; `None` to deliver bytes as the port sees fit.
+    fn set_terminator(&mut self, byte: Option<u8>) -> Result<()>;
+}
+
+/// The receive side of a [`ScannerPort`].
+pub trait ScannerRx: 'static + Send {
+    /// Wait at most `timeout` for data, then read everything received so far
+    /// into `buf`, up to and including the terminator if one is set.
+    /// `Ok(0)` if nothing arrived.
+    fn read_burst(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize>;
+}
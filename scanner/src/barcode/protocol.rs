@@ -0,0 +1,466 @@
+//! GM65 serial command protocol (datasheet section 8).
+//!
+//! Commands sent to the scanner are framed as:
+//!
+//! ```text
+//! 7E 00 | Types | Lens | Address (BE) | Datas | CRC (BE)
+//! ```
+//!
+//! and the scanner answers with:
+//!
+//! ```text
+//! 02 00 | Types | Lens | Datas | CRC (BE)
+//! ```
+//!
+//! The CRC is CRC-CCITT (poly 0x1021, init 0) over everything after the head.
+
+use std::fmt;
+
+/// Head of every command sent to the scanner.
+pub const COMMAND_HEAD: [u8; 2] = [0x7E, 0x00];
+
+/// Head of every reply sent by the scanner.
+pub const RESPONSE_HEAD: [u8; 2] = [0x02, 0x00];
+
+/// Maximum number of zone bytes in a single read command.
+pub const MAX_READ_LEN: usize = 256;
+
+/// Maximum number of zone bytes in a single write command.
+pub const MAX_WRITE_LEN: usize = 245;
+
+/// Zone bit addresses used by the firmware (datasheet 8.5).
+pub mod zone {
+    /// LED/mute/aim/illumination/scan mode flags.
+    pub const SETTINGS: u16 = 0x0000;
+    /// Writing `0x01` starts a scan in command-triggered mode.
+    pub const COMMAND_TRIGGER: u16 = 0x0002;
+    /// Pause before the same code is read again, in 100 ms units.
+    pub const READ_INTERVAL: u16 = 0x0005;
+    /// Time a single read may take, in 100 ms units; 0 = no limit.
+    pub const READ_TIMEOUT: u16 = 0x0006;
+    /// Payload encoding (bits 3-2) and output interface (bits 1-0).
+    pub const ENCODING: u16 = 0x000D;
+    /// Serial baud rate divider, low byte first (0x002A..=0x002B).
+    pub const BAUD_RATE: u16 = 0x002A;
+    /// Output formatting flags: tail type, prefix, Code ID, suffix, tail.
+    pub const OUTPUT_FORMAT: u16 = 0x0060;
+    /// Read-only model, hardware and software version (0x00E0..=0x00E2).
+    pub const PRODUCT_INFO: u16 = 0x00E0;
+}
+
+/// Command `Types` values.
+pub const TYPE_READ_ZONE: u8 = 0x07;
+pub const TYPE_WRITE_ZONE: u8 = 0x08;
+pub const TYPE_SAVE_TO_FLASH: u8 = 0x09;
+
+/// Reply `Types` value for a successful command.
+const STATUS_OK: u8 = 0x00;
+
+/// Compute the GM65 CRC-CCITT checksum (datasheet 8.1).
+pub fn crc_ccitt(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// A command understood by the GM65.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Read `len` consecutive zone bytes starting at `address` (1..=256).
+    ReadZone { address: u16, len: usize },
+
+    /// Write `data` to consecutive zone bytes starting at `address` (1..=245 bytes).
+    WriteZone { address: u16, data: Vec<u8> },
+
+    /// Save the current zone bits to the scanner's internal flash, so they
+    /// survive a power cycle.
+    SaveToFlash,
+}
+
+impl Command {
+    /// Encode the command into a complete frame, including the CRC.
+    pub fn encode(&self) -> Result<Vec<u8>, ProtocolError> {
+        // For reads `Lens` is always 1 and the count lives in `Datas`; for
+        // writes `Lens` is the number of data bytes.
+        let (ty, lens, address, datas) = match self {
+            Command::ReadZone { address, len } => {
+                if *len == 0 || *len > MAX_READ_LEN {
+                    return Err(ProtocolError::InvalidLength(*len));
+                }
+                // 0x00 means 256 bytes.
+                (TYPE_READ_ZONE, 0x01, *address, vec![*len as u8])
+            }
+            Command::WriteZone { address, data } => {
+                if data.is_empty() || data.len() > MAX_WRITE_LEN {
+                    return Err(ProtocolError::InvalidLength(data.len()));
+                }
+                (TYPE_WRITE_ZONE, data.len() as u8, *address, data.clone())
+            }
+            Command::SaveToFlash => (TYPE_SAVE_TO_FLASH, 0x01, 0x0000, vec![0x00]),
+        };
+
+        let mut frame = Vec::with_capacity(COMMAND_HEAD.len() + 4 + datas.len() + 2);
+        frame.extend_from_slice(&COMMAND_HEAD);
+        frame.push(ty);
+        frame.push(lens);
+        frame.extend_from_slice(&address.to_be_bytes());
+        frame.extend_from_slice(&datas);
+
+        let crc = crc_ccitt(&frame[COMMAND_HEAD.len()..]);
+        frame.extend_from_slice(&crc.to_be_bytes());
+        Ok(frame)
+    }
+
+    /// Whether `response` can be the reply to this command: a read returns
+    /// the bytes asked for, a write or save a single `0x00`, and a refusal
+    /// carries no data worth matching. Replies don't echo the command, so a
+    /// one-byte read of `0x00` and a write ack can't be told apart.
+    pub fn expects(&self, response: &Response) -> bool {
+        if !response.is_ok() {
+            return true;
+        }
+        match self {
+            Command::ReadZone { len, .. } => response.data.len() == *len,
+            Command::WriteZone { .. } | Command::SaveToFlash => response.data == [0x00],
+        }
+    }
+}
+
+/// A decoded reply from the scanner.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    /// Reply `Types` byte; `0x00` means success.
+    pub status: u8,
+    /// Reply payload: zone bytes for reads, a single `0x00` for writes.
+    pub data: Vec<u8>,
+}
+
+impl Response {
+    pub fn is_ok(&self) -> bool {
+        self.status == STATUS_OK
+    }
+
+    /// The payload, or [`ProtocolError::Rejected`] if the scanner refused
+    /// the command.
+    pub fn into_data(self) -> Result<Vec<u8>, ProtocolError> {
+        if self.is_ok() {
+            Ok(self.data)
+        } else {
+            Err(ProtocolError::Rejected(self.status))
+        }
+    }
+}
+
+/// Errors from encoding commands or decoding replies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProtocolError {
+    /// Command payload length is outside what the GM65 accepts.
+    InvalidLength(usize),
+    /// Reply CRC did not match its contents.
+    BadCrc { expected: u16, actual: u16 },
+    /// Scanner answered with a non-zero status.
+    Rejected(u8),
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProtocolError::InvalidLength(len) => write!(f, "invalid zone length {len}"),
+            ProtocolError::BadCrc { expected, actual } => {
+                write!(f, "bad CRC: expected {expected:04X}, got {actual:04X}")
+            }
+            ProtocolError::Rejected(status) => {
+                write!(f, "scanner rejected command ({status:#04X})")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParseState {
+    Head0,
+    Head1,
+    Types,
+    Lens,
+    Datas,
+    Crc0,
+    Crc1,
+}
+
+/// Incremental parser for scanner replies.
+///
+/// Bytes are fed one at a time; anything before a `02 00` head is skipped.
+pub struct ResponseParser {
+    state: ParseState,
+    status: u8,
+    len: usize,
+    data: Vec<u8>,
+    crc: u16,
+}
+
+impl Default for ResponseParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResponseParser {
+    pub fn new() -> Self {
+        Self {
+            state: ParseState::Head0,
+            status: 0,
+            len: 0,
+            data: Vec::new(),
+            crc: 0,
+        }
+    }
+
+    /// Discard any partially parsed reply.
+    pub fn reset(&mut self) {
+        self.state = ParseState::Head0;
+        self.data.clear();
+    }
+
+    /// How many of the bytes fed so far belong to a reply that isn't
+    /// complete yet; earlier bytes weren't part of any reply.
+    pub fn partial_len(&self) -> usize {
+        match self.state {
+            ParseState::Head0 => 0,
+            ParseState::Head1 => 1,
+            ParseState::Types => 2,
+            ParseState::Lens => 3,
+            ParseState::Datas | ParseState::Crc0 => 4 + self.data.len(),
+            ParseState::Crc1 => 5 + self.data.len(),
+        }
+    }
+
+    /// Feed one byte; returns a result once a complete reply has been seen.
+    pub fn push(&mut self, byte: u8) -> Option<Result<Response, ProtocolError>> {
+        match self.state {
+            ParseState::Head0 => {
+                if byte == RESPONSE_HEAD[0] {
+                    self.state = ParseState::Head1;
+                }
+            }
+            ParseState::Head1 => {
+                self.state = if byte == RESPONSE_HEAD[1] {
+                    ParseState::Types
+                } else if byte == RESPONSE_HEAD[0] {
+                    ParseState::Head1
+                } else {
+                    ParseState::Head0
+                };
+            }
+            ParseState::Types => {
+                self.status = byte;
+                self.state = ParseState::Lens;
+            }
+            ParseState::Lens => {
+                // 0x00 means 256 bytes.
+                self.len = if byte == 0 { 256 } else { byte as usize };
+                self.data.clear();
+                self.state = ParseState::Datas;
+            }
+            ParseState::Datas => {
+                self.data.push(byte);
+                if self.data.len() == self.len {
+                    self.state = ParseState::Crc0;
+                }
+            }
+            ParseState::Crc0 => {
+                self.crc = (byte as u16) << 8;
+                self.state = ParseState::Crc1;
+            }
+            ParseState::Crc1 => {
+                self.crc |= byte as u16;
+                self.state = ParseState::Head0;
+                return Some(self.finish());
+            }
+        }
+        None
+    }
+
+    fn finish(&mut self) -> Result<Response, ProtocolError> {
+        let mut covered = Vec::with_capacity(self.data.len() + 2);
+        covered.push(self.status);
+        covered.push(self.data.len() as u8);
+        covered.extend_from_slice(&self.data);
+
+        let expected = crc_ccitt(&covered);
+        if expected != self.crc {
+            return Err(ProtocolError::BadCrc {
+                expected,
+                actual: self.crc,
+            });
+        }
+
+        Ok(Response {
+            status: self.status,
+            data: std::mem::take(&mut self.data),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The first reply a fresh parser completes from `bytes`.
+    fn parse(bytes: &[u8]) -> Option<Result<Response, ProtocolError>> {
+        let mut parser = ResponseParser::new();
+        bytes.iter().find_map(|&byte| parser.push(byte))
+    }
+
+    #[test]
+    fn encodes_trigger_write() {
+        // Datasheet 8.3 with the real CRC in place of `AB CD`.
+        let write = Command::WriteZone {
+            address: zone::COMMAND_TRIGGER,
+            data: vec![0x01],
+        };
+        assert_eq!(
+            write.encode().unwrap(),
+            [0x7E, 0x00, 0x08, 0x01, 0x00, 0x02, 0x01, 0x02, 0xDA]
+        );
+    }
+
+    #[test]
+    fn encodes_save_to_flash() {
+        assert_eq!(
+            Command::SaveToFlash.encode().unwrap(),
+            [0x7E, 0x00, 0x09, 0x01, 0x00, 0x00, 0x00, 0xDE, 0xC8]
+        );
+    }
+
+    #[test]
+    fn encodes_zone_read() {
+        let read = Command::ReadZone {
+            address: zone::BAUD_RATE,
+            len: 2,
+        };
+        assert_eq!(
+            read.encode().unwrap(),
+            [0x7E, 0x00, 0x07, 0x01, 0x00, 0x2A, 0x02, 0xD8, 0x0F]
+        );
+    }
+
+    #[test]
+    fn encodes_256_byte_read_as_zero() {
+        let read = Command::ReadZone {
+            address: zone::SETTINGS,
+            len: MAX_READ_LEN,
+        };
+        assert_eq!(read.encode().unwrap()[6], 0x00);
+    }
+
+    #[test]
+    fn rejects_out_of_range_lengths() {
+        for len in [0, MAX_READ_LEN + 1] {
+            let read = Command::ReadZone { address: 0, len };
+            assert_eq!(read.encode(), Err(ProtocolError::InvalidLength(len)));
+        }
+        for len in [0, MAX_WRITE_LEN + 1] {
+            let write = Command::WriteZone {
+                address: 0,
+                data: vec![0; len],
+            };
+            assert_eq!(write.encode(), Err(ProtocolError::InvalidLength(len)));
+        }
+    }
+
+    #[test]
+    fn parses_write_ack() {
+        let ack = parse(&[0x02, 0x00, 0x00, 0x01, 0x00, 0x33, 0x31]).unwrap();
+        assert_eq!(ack.unwrap().into_data(), Ok(vec![0x00]));
+    }
+
+    #[test]
+    fn skips_bytes_before_the_head() {
+        let reply = [0x41, 0x02, 0x02, 0x00, 0x00, 0x02, 0x39, 0x01, 0xC1, 0x4C];
+        assert_eq!(parse(&reply).unwrap().unwrap().data, [0x39, 0x01]);
+    }
+
+    #[test]
+    fn reports_bad_crc() {
+        let reply = parse(&[0x02, 0x00, 0x00, 0x01, 0x00, 0x33, 0x32]).unwrap();
+        assert_eq!(
+            reply,
+            Err(ProtocolError::BadCrc {
+                expected: 0x3331,
+                actual: 0x3332,
+            })
+        );
+    }
+
+    #[test]
+    fn reports_non_zero_status_as_rejected() {
+        let reply = parse(&[0x02, 0x00, 0x01, 0x01, 0x00, 0x04, 0x01]).unwrap();
+        assert_eq!(
+            reply.unwrap().into_data(),
+            Err(ProtocolError::Rejected(0x01))
+        );
+    }
+
+    #[test]
+    fn matches_replies_to_their_command() {
+        let read = Command::ReadZone {
+            address: zone::BAUD_RATE,
+            len: 2,
+        };
+        let write = Command::WriteZone {
+            address: zone::COMMAND_TRIGGER,
+            data: vec![0x01],
+        };
+        let ack = Response {
+            status: 0x00,
+            data: vec![0x00],
+        };
+        let baud = Response {
+            status: 0x00,
+            data: vec![0x39, 0x01],
+        };
+        let refused = Response {
+            status: 0x01,
+            data: vec![0x00],
+        };
+
+        assert!(read.expects(&baud));
+        assert!(!read.expects(&ack));
+        assert!(write.expects(&ack));
+        assert!(!write.expects(&baud));
+        assert!(Command::SaveToFlash.expects(&ack));
+        assert!(read.expects(&refused));
+    }
+
+    #[test]
+    fn counts_the_bytes_of_a_partial_reply() {
+        let mut parser = ResponseParser::new();
+        parser.push(b'Q');
+        assert_eq!(parser.partial_len(), 0);
+        parser.push(0x02);
+        assert_eq!(parser.partial_len(), 1);
+        parser.push(b'x');
+        assert_eq!(parser.partial_len(), 0);
+        // A repeated `02` restarts the head.
+        parser.push(0x02);
+        parser.push(0x02);
+        assert_eq!(parser.partial_len(), 1);
+        for (i, &byte) in [0x00, 0x00, 0x01, 0x00, 0x33].iter().enumerate() {
+            parser.push(byte);
+            assert_eq!(parser.partial_len(), i + 2);
+        }
+        assert!(parser.push(0x31).is_some());
+        assert_eq!(parser.partial_len(), 0);
+    }
+}
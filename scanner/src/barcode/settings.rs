@@ -0,0 +1,753 @@
+//! The GM65's zone-bit settings as a typed struct, and profiles as JSON.
+//!
+//! [`ScannerSettings`] covers the documented zones from 0x0000 to 0x00B2
+//! (datasheet 8.5) except those the worker manages itself: the command
+//! trigger, the Code ID output flag and the Code ID characters, which symbology
+//! detection relies on. Applying settings only rewrites the bytes that
+//! change, and keeps reserved bits as the scanner has them.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{json, Map, Value};
+
+use super::{BaudRate, PayloadEncoding, ScanMode, Symbology};
+
+/// Bytes of zone bits covered, starting at 0x0000.
+pub const ZONE_LEN: usize = 0xB3;
+
+const SETTINGS: usize = 0x00;
+const STABILIZATION: usize = 0x04;
+const READ_INTERVAL: usize = 0x05;
+const READ_TIMEOUT: usize = 0x06;
+const SLEEP_HIGH: usize = 0x07;
+const SLEEP_LOW: usize = 0x08;
+const BEEP_TONE: usize = 0x0A;
+const BEEP_DURATION: usize = 0x0B;
+const ENCODING: usize = 0x0D;
+const DECODE_SOUND: usize = 0x0E;
+const BAUD_RATE: usize = 0x2A;
+const BARCODES: usize = 0x2C;
+const CODABAR: usize = 0x3C;
+const OUTPUT_FORMAT: usize = 0x60;
+const AFFIX_LENGTHS: usize = 0x62;
+const PREFIX: usize = 0x63;
+const SUFFIX: usize = 0x72;
+const RF_LENGTH: usize = 0x81;
+const RF: usize = 0x82;
+const DATA_CUT: usize = 0xB0;
+const CUT_START: usize = 0xB1;
+const CUT_END: usize = 0xB2;
+
+/// Longest prefix, suffix or RF field.
+const MAX_AFFIX_LEN: usize = 15;
+/// Unit of the timing zones.
+const TIME_UNIT_MS: u64 = 100;
+
+/// Symbologies with min/max length zones, and the address of the min byte
+/// (max follows). The RSS variants share one pair, listed under RSS-14.
+const LENGTH_ZONES: [(Symbology, usize); 10] = [
+    (Symbology::Code128, 0x34),
+    (Symbology::Code39, 0x37),
+    (Symbology::Code93, 0x3A),
+    (Symbology::Codabar, 0x3D),
+    (Symbology::Interleaved2of5, 0x41),
+    (Symbology::Industrial2of5, 0x44),
+    (Symbology::Matrix2of5, 0x47),
+    (Symbology::Code11, 0x4A),
+    (Symbology::MsiPlessey, 0x4D),
+    (Symbology::Rss14, 0x52),
+];
+
+// Output format flags (zone 0x0060).
+const RF_ON: u8 = 1 << 4;
+const PREFIX_ON: u8 = 1 << 3;
+const SUFFIX_ON: u8 = 1 << 1;
+const TAIL_ON: u8 = 1 << 0;
+
+/// Aiming or illumination light (zone 0x0000 bits 5-4 and 3-2).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lighting {
+    Off,
+    /// On while scanning (module default).
+    Standard,
+    AlwaysOn,
+}
+
+impl Lighting {
+    /// Position of the aim mode in zone 0x0000.
+    pub(super) const AIM_SHIFT: u8 = 4;
+    /// Position of the illumination mode in zone 0x0000.
+    pub(super) const ILLUMINATION_SHIFT: u8 = 2;
+    pub(super) const MASK: u8 = 0b11;
+
+    pub fn bits(self) -> u8 {
+        match self {
+            Lighting::Off => 0b00,
+            Lighting::Standard => 0b01,
+            Lighting::AlwaysOn => 0b10,
+        }
+    }
+
+    pub fn from_bits(bits: u8) -> Self {
+        match bits & Self::MASK {
+            0b00 => Lighting::Off,
+            0b01 => Lighting::Standard,
+            _ => Lighting::AlwaysOn,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Lighting::Off => "off",
+            Lighting::Standard => "standard",
+            Lighting::AlwaysOn => "always-on",
+        }
+    }
+}
+
+impl FromStr for Lighting {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "off" => Ok(Lighting::Off),
+            "standard" => Ok(Lighting::Standard),
+            "always-on" => Ok(Lighting::AlwaysOn),
+            _ => bail!("Unknown lighting mode: {s}"),
+        }
+    }
+}
+
+/// Which symbologies are decoded (zone 0x002C bits 2-1).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbologyFilter {
+    /// No barcode is read.
+    NoneAllowed,
+    /// Every symbology is read, regardless of the per-symbology flags.
+    All,
+    /// The per-symbology flags decide (module default).
+    Selected,
+}
+
+impl SymbologyFilter {
+    fn bits(self) -> u8 {
+        match self {
+            SymbologyFilter::NoneAllowed => 0b00,
+            SymbologyFilter::All => 0b01,
+            SymbologyFilter::Selected => 0b10,
+        }
+    }
+
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0b00 => SymbologyFilter::NoneAllowed,
+            0b01 => SymbologyFilter::All,
+            _ => SymbologyFilter::Selected,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SymbologyFilter::NoneAllowed => "none",
+            SymbologyFilter::All => "all",
+            SymbologyFilter::Selected => "selected",
+        }
+    }
+}
+
+impl FromStr for SymbologyFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "none" => Ok(SymbologyFilter::NoneAllowed),
+            "all" => Ok(SymbologyFilter::All),
+            "selected" => Ok(SymbologyFilter::Selected),
+            _ => bail!("Unknown symbology filter: {s}"),
+        }
+    }
+}
+
+/// Bytes appended to each scan (zone 0x0060 bits 6-5 and 0).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tail {
+    Cr,
+    CrLf,
+    Tab,
+    /// No tail (module default).
+    None,
+}
+
+impl Tail {
+    fn bits(self) -> u8 {
+        match self {
+            Tail::Cr => 0b00,
+            Tail::CrLf => 0b01,
+            Tail::Tab => 0b10,
+            Tail::None => 0b11,
+        }
+    }
+
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0b00 => Tail::Cr,
+            0b01 => Tail::CrLf,
+            0b10 => Tail::Tab,
+            _ => Tail::None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Tail::Cr => "cr",
+            Tail::CrLf => "crlf",
+            Tail::Tab => "tab",
+            Tail::None => "none",
+        }
+    }
+}
+
+impl FromStr for Tail {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "cr" => Ok(Tail::Cr),
+            "crlf" => Ok(Tail::CrLf),
+            "tab" => Ok(Tail::Tab),
+            "none" => Ok(Tail::None),
+            _ => bail!("Unknown tail: {s}"),
+        }
+    }
+}
+
+/// Which part of the data is output (zone 0x00B0 bits 1-0), using the cut
+/// lengths in zones 0x00B1 and 0x00B2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataCut {
+    /// The whole payload (module default).
+    Whole,
+    Start,
+    End,
+    Center,
+}
+
+impl DataCut {
+    fn bits(self) -> u8 {
+        match self {
+            DataCut::Whole => 0b00,
+            DataCut::Start => 0b01,
+            DataCut::End => 0b10,
+            DataCut::Center => 0b11,
+        }
+    }
+
+    fn from_bits(bits: u8) -> Self {
+        match bits & 0b11 {
+            0b00 => DataCut::Whole,
+            0b01 => DataCut::Start,
+            0b10 => DataCut::End,
+            _ => DataCut::Center,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            DataCut::Whole => "whole",
+            DataCut::Start => "start",
+            DataCut::End => "end",
+            DataCut::Center => "center",
+        }
+    }
+}
+
+impl FromStr for DataCut {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "whole" => Ok(DataCut::Whole),
+            "start" => Ok(DataCut::Start),
+            "end" => Ok(DataCut::End),
+            "center" => Ok(DataCut::Center),
+            _ => bail!("Unknown data cut: {s}"),
+        }
+    }
+}
+
+/// Everything a profile carries, decoded from the zone bits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScannerSettings {
+    pub scan_mode: ScanMode,
+    /// Light the LED after a good read.
+    pub led_on_success: bool,
+    /// Sound the buzzer at all (mute off).
+    pub sound: bool,
+    pub aim: Lighting,
+    pub illumination: Lighting,
+    /// Time for image stabilization before decoding.
+    pub stabilization: Duration,
+    /// Pause between reads in continuous mode.
+    pub read_interval: Duration,
+    /// How long a single read may take; zero means no limit.
+    pub read_timeout: Duration,
+    pub auto_sleep: bool,
+    /// Idle time before auto sleep.
+    pub idle_time: Duration,
+    /// Good-read tone: 0 for the piezo buzzer's own, otherwise value × 20 Hz.
+    pub beep_tone: u8,
+    pub beep_duration: Duration,
+    /// Beep on a successful decode.
+    pub decode_sound: bool,
+    pub encoding: PayloadEncoding,
+    pub baud: BaudRate,
+    pub symbology_filter: SymbologyFilter,
+    /// Read labels at any orientation.
+    pub rotate_360: bool,
+    /// Symbologies whose enable flag is set.
+    pub enabled: BTreeSet<Symbology>,
+    /// Min and max payload length of the symbologies that have limits; the
+    /// RSS variants share the `Rss14` entry.
+    pub lengths: BTreeMap<Symbology, (u8, u8)>,
+    /// Output Codabar start/stop characters.
+    pub codabar_start_stop: bool,
+    pub tail: Tail,
+    /// Empty when prefix output is off.
+    pub prefix: Vec<u8>,
+    /// Empty when suffix output is off.
+    pub suffix: Vec<u8>,
+    /// Empty when RF output is off.
+    pub rf: Vec<u8>,
+    pub data_cut: DataCut,
+    /// Bytes cut from the start and end by [`DataCut`].
+    pub cut_lengths: (u8, u8),
+}
+
+impl ScannerSettings {
+    /// Decode the zone bits from 0x0000, as read from the scanner.
+    pub fn from_zones(zones: &[u8]) -> Result<Self> {
+        if zones.len() < ZONE_LEN {
+            bail!("Expected {ZONE_LEN} zone bytes, got {}", zones.len());
+        }
+        let flag = |address: usize, bit: u8| zones[address] & (1 << bit) != 0;
+        let time = |address: usize| time_from_units(zones[address]);
+        let output = zones[OUTPUT_FORMAT];
+        let affix = |on: u8, address: usize, len: u8| {
+            let len = (len as usize).min(MAX_AFFIX_LEN);
+            if output & on != 0 {
+                zones[address..address + len].to_vec()
+            } else {
+                Vec::new()
+            }
+        };
+
+        let idle_units = u16::from_be_bytes([zones[SLEEP_HIGH] & 0x7F, zones[SLEEP_LOW]]);
+        let tail = if output & TAIL_ON != 0 {
+            Tail::from_bits(output >> 5)
+        } else {
+            Tail::None
+        };
+
+        Ok(Self {
+            scan_mode: ScanMode::from_bits(zones[SETTINGS]),
+            led_on_success: flag(SETTINGS, 7),
+            sound: flag(SETTINGS, 6),
+            aim: Lighting::from_bits(zones[SETTINGS] >> Lighting::AIM_SHIFT),
+            illumination: Lighting::from_bits(zones[SETTINGS] >> Lighting::ILLUMINATION_SHIFT),
+            stabilization: time(STABILIZATION),
+            read_interval: time(READ_INTERVAL),
+            read_timeout: time(READ_TIMEOUT),
+            auto_sleep: flag(SLEEP_HIGH, 7),
+            idle_time: Duration::from_millis(idle_units as u64 * TIME_UNIT_MS),
+            beep_tone: zones[BEEP_TONE],
+            beep_duration: Duration::from_millis(zones[BEEP_DURATION] as u64),
+            decode_sound: flag(DECODE_SOUND, 2),
+            encoding: PayloadEncoding::from_bits(zones[ENCODING]),
+            baud: BaudRate::from_zone_bytes([zones[BAUD_RATE], zones[BAUD_RATE + 1]])
+                .context("Unknown baud rate divider")?,
+            symbology_filter: SymbologyFilter::from_bits(zones[BARCODES] >> 1),
+            rotate_360: flag(BARCODES, 0),
+            enabled: Symbology::ALL
+                .into_iter()
+                .filter(|sym| flag(sym.zone() as usize, 0))
+                .collect(),
+            lengths: LENGTH_ZONES
+                .iter()
+                .map(|&(sym, address)| (sym, (zones[address], zones[address + 1])))
+                .collect(),
+            codabar_start_stop: flag(CODABAR, 1),
+            tail,
+            prefix: affix(PREFIX_ON, PREFIX, zones[AFFIX_LENGTHS] >> 4),
+            suffix: affix(SUFFIX_ON, SUFFIX, zones[AFFIX_LENGTHS] & 0x0F),
+            rf: affix(RF_ON, RF, zones[RF_LENGTH] & 0x0F),
+            data_cut: DataCut::from_bits(zones[DATA_CUT]),
+            cut_lengths: (zones[CUT_START], zones[CUT_END]),
+        })
+    }
+
+    /// Encode into `zones`, a copy of the scanner's current zone bits from
+    /// 0x0000. The baud rate is left alone: the worker has to follow a
+    /// change, so it is applied separately.
+    pub fn write_zones(&self, zones: &mut [u8]) -> Result<()> {
+        if zones.len() < ZONE_LEN {
+            bail!("Expected {ZONE_LEN} zone bytes, got {}", zones.len());
+        }
+        let set_bits = |zones: &mut [u8], address: usize, mask: u8, value: u8| {
+            zones[address] = (zones[address] & !mask) | (value & mask);
+        };
+        let set_flag = |zones: &mut [u8], address: usize, bit: u8, on: bool| {
+            set_bits(zones, address, 1 << bit, if on { 0xFF } else { 0 });
+        };
+
+        set_bits(zones, SETTINGS, ScanMode::MASK, self.scan_mode.bits());
+        set_flag(zones, SETTINGS, 7, self.led_on_success);
+        set_flag(zones, SETTINGS, 6, self.sound);
+        for (mode, shift) in [
+            (self.aim, Lighting::AIM_SHIFT),
+            (self.illumination, Lighting::ILLUMINATION_SHIFT),
+        ] {
+            set_bits(
+                zones,
+                SETTINGS,
+                Lighting::MASK << shift,
+                mode.bits() << shift,
+            );
+        }
+
+        zones[STABILIZATION] = time_units(self.stabilization, "stabilization")?;
+        zones[READ_INTERVAL] = time_units(self.read_interval, "read interval")?;
+        zones[READ_TIMEOUT] = time_units(self.read_timeout, "read timeout")?;
+        let idle_units = u16::try_from(self.idle_time.as_millis() / TIME_UNIT_MS as u128)
+            .ok()
+            .filter(|units| *units <= 0x7FFF)
+            .context("Idle time is longer than 3276.7 s")?;
+        let [idle_high, idle_low] = idle_units.to_be_bytes();
+        zones[SLEEP_HIGH] = idle_high | if self.auto_sleep { 0x80 } else { 0 };
+        zones[SLEEP_LOW] = idle_low;
+
+        zones[BEEP_TONE] = self.beep_tone;
+        zones[BEEP_DURATION] = u8::try_from(self.beep_duration.as_millis())
+            .context("Beep duration is longer than 255 ms")?;
+        set_flag(zones, DECODE_SOUND, 2, self.decode_sound);
+        set_bits(zones, ENCODING, PayloadEncoding::MASK, self.encoding.bits());
+
+        set_bits(
+            zones,
+            BARCODES,
+            0b11 << 1,
+            self.symbology_filter.bits() << 1,
+        );
+        set_flag(zones, BARCODES, 0, self.rotate_360);
+        for sym in Symbology::ALL {
+            set_flag(zones, sym.zone() as usize, 0, self.enabled.contains(&sym));
+        }
+        for (sym, (min, max)) in &self.lengths {
+            let (_, address) = LENGTH_ZONES
+                .iter()
+                .find(|(length_sym, _)| length_sym == sym)
+                .with_context(|| format!("{sym} has no length limits"))?;
+            zones[*address] = *min;
+            zones[*address + 1] = *max;
+        }
+        set_flag(zones, CODABAR, 1, self.codabar_start_stop);
+
+        // With the tail off its type bits are left as stored, so reading
+        // and writing back unchanged settings rewrites nothing.
+        set_flag(zones, OUTPUT_FORMAT, 0, self.tail != Tail::None);
+        if self.tail != Tail::None {
+            set_bits(zones, OUTPUT_FORMAT, 0b11 << 5, self.tail.bits() << 5);
+        }
+        for (affix, name) in [
+            (&self.prefix, "Prefix"),
+            (&self.suffix, "Suffix"),
+            (&self.rf, "RF"),
+        ] {
+            if affix.len() > MAX_AFFIX_LEN {
+                bail!("{name} is longer than {MAX_AFFIX_LEN} bytes");
+            }
+        }
+        set_flag(zones, OUTPUT_FORMAT, 3, !self.prefix.is_empty());
+        set_flag(zones, OUTPUT_FORMAT, 1, !self.suffix.is_empty());
+        set_flag(zones, OUTPUT_FORMAT, 4, !self.rf.is_empty());
+        // An empty field keeps its stored content, only switched off.
+        if !self.prefix.is_empty() {
+            set_bits(zones, AFFIX_LENGTHS, 0xF0, (self.prefix.len() as u8) << 4);
+            zones[PREFIX..PREFIX + self.prefix.len()].copy_from_slice(&self.prefix);
+        }
+        if !self.suffix.is_empty() {
+            set_bits(zones, AFFIX_LENGTHS, 0x0F, self.suffix.len() as u8);
+            zones[SUFFIX..SUFFIX + self.suffix.len()].copy_from_slice(&self.suffix);
+        }
+        if !self.rf.is_empty() {
+            set_bits(zones, RF_LENGTH, 0x0F, self.rf.len() as u8);
+            zones[RF..RF + self.rf.len()].copy_from_slice(&self.rf);
+        }
+
+        set_bits(zones, DATA_CUT, 0b11, self.data_cut.bits());
+        (zones[CUT_START], zones[CUT_END]) = self.cut_lengths;
+        Ok(())
+    }
+
+    /// The profile as a JSON object. Durations are in milliseconds and byte
+    /// strings in hex.
+    pub fn to_json(&self) -> String {
+        let ms = |duration: Duration| duration.as_millis() as u64;
+        let enabled: Vec<&str> = self.enabled.iter().map(|sym| sym.as_str()).collect();
+        let lengths: Map<String, Value> = self
+            .lengths
+            .iter()
+            .map(|(sym, (min, max))| (sym.as_str().to_string(), json!([min, max])))
+            .collect();
+
+        let profile = json!({
+            "scan_mode": self.scan_mode.as_str(),
+            "led_on_success": self.led_on_success,
+            "sound": self.sound,
+            "aim": self.aim.as_str(),
+            "illumination": self.illumination.as_str(),
+            "stabilization_ms": ms(self.stabilization),
+            "read_interval_ms": ms(self.read_interval),
+            "read_timeout_ms": ms(self.read_timeout),
+            "auto_sleep": self.auto_sleep,
+            "idle_time_ms": ms(self.idle_time),
+            "beep_tone": self.beep_tone,
+            "beep_duration_ms": ms(self.beep_duration),
+            "decode_sound": self.decode_sound,
+            "encoding": self.encoding.as_str(),
+            "baud_rate": self.baud.bps(),
+            "symbology_filter": self.symbology_filter.as_str(),
+            "rotate_360": self.rotate_360,
+            "enabled": enabled,
+            "lengths": lengths,
+            "codabar_start_stop": self.codabar_start_stop,
+            "tail": self.tail.as_str(),
+            "prefix": hex(&self.prefix),
+            "suffix": hex(&self.suffix),
+            "rf": hex(&self.rf),
+            "data_cut": self.data_cut.as_str(),
+            "cut_start": self.cut_lengths.0,
+            "cut_end": self.cut_lengths.1,
+        });
+        serde_json::to_string_pretty(&profile).unwrap_or_default()
+    }
+
+    /// Overwrite the fields present in a JSON profile. Unknown keys are
+    /// rejected so a typo doesn't go unnoticed.
+    pub fn update_from_json(&mut self, profile: &str) -> Result<()> {
+        let profile: Value = serde_json::from_str(profile).context("Profile is not valid JSON")?;
+        let profile = profile
+            .as_object()
+            .context("Profile must be a JSON object")?;
+
+        for (key, value) in profile {
+            self.update_field(key, value)
+                .with_context(|| format!("Invalid profile field \"{key}\""))?;
+        }
+        Ok(())
+    }
+
+    fn update_field(&mut self, key: &str, value: &Value) -> Result<()> {
+        let text = || value.as_str().context("expected a string");
+        let flag = || value.as_bool().context("expected true or false");
+        let byte = || {
+            value
+                .as_u64()
+                .and_then(|n| u8::try_from(n).ok())
+                .context("expected a number from 0 to 255")
+        };
+        let ms = || {
+            value
+                .as_u64()
+                .map(Duration::from_millis)
+                .context("expected a number of milliseconds")
+        };
+
+        match key {
+            "scan_mode" => self.scan_mode = text()?.parse()?,
+            "led_on_success" => self.led_on_success = flag()?,
+            "sound" => self.sound = flag()?,
+            "aim" => self.aim = text()?.parse()?,
+            "illumination" => self.illumination = text()?.parse()?,
+            "stabilization_ms" => self.stabilization = ms()?,
+            "read_interval_ms" => self.read_interval = ms()?,
+            "read_timeout_ms" => self.read_timeout = ms()?,
+            "auto_sleep" => self.auto_sleep = flag()?,
+            "idle_time_ms" => self.idle_time = ms()?,
+            "beep_tone" => self.beep_tone = byte()?,
+            "beep_duration_ms" => self.beep_duration = ms()?,
+            "decode_sound" => self.decode_sound = flag()?,
+            "encoding" => self.encoding = text()?.parse()?,
+            "baud_rate" => {
+                let bps = value.as_u64().context("expected a number")?;
+                self.baud = u32::try_from(bps)
+                    .ok()
+                    .and_then(BaudRate::from_bps)
+                    .with_context(|| format!("unsupported baud rate {bps}"))?;
+            }
+            "symbology_filter" => self.symbology_filter = text()?.parse()?,
+            "rotate_360" => self.rotate_360 = flag()?,
+            "enabled" => {
+                self.enabled = value
+                    .as_array()
+                    .context("expected a list of symbologies")?
+                    .iter()
+                    .map(|sym| sym.as_str().context("expected a symbology name")?.parse())
+                    .collect::<Result<_>>()?;
+            }
+            "lengths" => {
+                let lengths = value.as_object().context("expected an object")?;
+                for (sym, range) in lengths {
+                    let sym: Symbology = sym.parse()?;
+                    if !LENGTH_ZONES
+                        .iter()
+                        .any(|(length_sym, _)| *length_sym == sym)
+                    {
+                        bail!("{sym} has no length limits");
+                    }
+                    let range = match range.as_array().map(Vec::as_slice) {
+                        Some([min, max]) => (min.as_u64(), max.as_u64()),
+                        _ => (None, None),
+                    };
+                    let (Some(min), Some(max)) = range else {
+                        bail!("expected [min, max] for {sym}");
+                    };
+                    let min = u8::try_from(min).map_err(|_| anyhow!("{sym} min is over 255"))?;
+                    let max = u8::try_from(max).map_err(|_| anyhow!("{sym} max is over 255"))?;
+                    self.lengths.insert(sym, (min, max));
+                }
+            }
+            "codabar_start_stop" => self.codabar_start_stop = flag()?,
+            "tail" => self.tail = text()?.parse()?,
+            "prefix" => self.prefix = parse_hex(text()?)?,
+            "suffix" => self.suffix = parse_hex(text()?)?,
+            "rf" => self.rf = parse_hex(text()?)?,
+            "data_cut" => self.data_cut = text()?.parse()?,
+            "cut_start" => self.cut_lengths.0 = byte()?,
+            "cut_end" => self.cut_lengths.1 = byte()?,
+            _ => bail!("unknown field"),
+        }
+        Ok(())
+    }
+}
+
+/// The runs of bytes that differ between two zone images, as
+/// `(address, data)` writes.
+pub fn changed_runs(current: &[u8], updated: &[u8]) -> Vec<(u16, Vec<u8>)> {
+    let mut runs: Vec<(u16, Vec<u8>)> = Vec::new();
+    for (address, (old, new)) in current.iter().zip(updated).enumerate() {
+        if old == new {
+            continue;
+        }
+        match runs.last_mut() {
+            Some((start, data)) if *start as usize + data.len() == address => data.push(*new),
+            _ => runs.push((address as u16, vec![*new])),
+        }
+    }
+    runs
+}
+
+/// Duration of a timing zone value.
+pub(super) fn time_from_units(units: u8) -> Duration {
+    Duration::from_millis(units as u64 * TIME_UNIT_MS)
+}
+
+/// A timing zone value: 100 ms units, at most 25.5 s. Zero means no limit,
+/// so shorter non-zero durations are rejected rather than rounded to it.
+pub(super) fn time_units(duration: Duration, name: &str) -> Result<u8> {
+    if !duration.is_zero() && duration < Duration::from_millis(TIME_UNIT_MS) {
+        bail!("The {name} is shorter than 0.1 s");
+    }
+    u8::try_from(duration.as_millis() / TIME_UNIT_MS as u128)
+        .map_err(|_| anyhow!("The {name} is longer than 25.5 s"))
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02X}")).collect()
+}
+
+fn parse_hex(s: &str) -> Result<Vec<u8>> {
+    s.as_bytes()
+        .chunks(2)
+        .map(|pair| {
+            std::str::from_utf8(pair)
+                .ok()
+                .filter(|pair| pair.len() == 2)
+                .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                .with_context(|| format!("invalid hex string \"{s}\""))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::mock::MockScanner;
+    use super::*;
+
+    /// Zone bits of a scanner fresh from the factory.
+    fn factory_zones() -> Vec<u8> {
+        let scanner = MockScanner::new();
+        (0..ZONE_LEN as u16)
+            .map(|address| scanner.zone(address))
+            .collect()
+    }
+
+    /// Runs `settings` would rewrite on a scanner holding `zones`.
+    fn rewrites(settings: &ScannerSettings, zones: &[u8]) -> Vec<(u16, Vec<u8>)> {
+        let mut updated = zones.to_vec();
+        settings.write_zones(&mut updated).unwrap();
+        changed_runs(zones, &updated)
+    }
+
+    #[test]
+    fn unchanged_settings_rewrite_nothing() {
+        for output_format in [0b0000_0100, 0b0010_0100, 0b0110_0100, 0b0100_0101] {
+            let mut zones = factory_zones();
+            zones[OUTPUT_FORMAT] = output_format;
+            let settings = ScannerSettings::from_zones(&zones).unwrap();
+            assert_eq!(rewrites(&settings, &zones), [], "{output_format:08b}");
+        }
+    }
+
+    #[test]
+    fn switching_the_tail_on_and_off() {
+        let mut zones = factory_zones();
+        // CRLF stored but switched off, Code ID on.
+        zones[OUTPUT_FORMAT] = 0b0010_0100;
+        let mut settings = ScannerSettings::from_zones(&zones).unwrap();
+        assert_eq!(settings.tail, Tail::None);
+
+        settings.tail = Tail::Tab;
+        assert_eq!(
+            rewrites(&settings, &zones),
+            [(OUTPUT_FORMAT as u16, vec![0b0100_0101])]
+        );
+
+        zones[OUTPUT_FORMAT] = 0b0100_0101;
+        settings.tail = Tail::None;
+        assert_eq!(
+            rewrites(&settings, &zones),
+            [(OUTPUT_FORMAT as u16, vec![0b0100_0100])]
+        );
+    }
+
+    #[test]
+    fn exported_profile_imports_unchanged() {
+        let mut zones = factory_zones();
+        zones[OUTPUT_FORMAT] = 0b0010_0100;
+        let mut exported = ScannerSettings::from_zones(&zones).unwrap();
+        exported.aim = Lighting::AlwaysOn;
+        exported.read_timeout = Duration::from_millis(2500);
+        exported.prefix = b"AB".to_vec();
+        exported.enabled.remove(&Symbology::Code11);
+        exported.write_zones(&mut zones).unwrap();
+
+        let profile = exported.to_json();
+        let mut imported = ScannerSettings::from_zones(&factory_zones()).unwrap();
+        imported.update_from_json(&profile).unwrap();
+        assert_eq!(imported, exported);
+        assert_eq!(rewrites(&imported, &zones), []);
+    }
+}
@@ -0,0 +1,61 @@
+//! Counters describing how a scanner is doing since start or the last reset.
+
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use super::Symbology;
+
+/// Scan and error counts kept by the worker.
+///
+/// A dirty lens shows up as a rising share of invalid reads or dedupe drops
+/// (the same label read over and over); a failing cable as read errors.
+#[derive(Debug, Clone)]
+pub struct ScanStats {
+    /// Reads reported to `on_event`, of any outcome.
+    pub total_scans: u32,
+    /// Reported reads by symbology; `None` without Code ID output.
+    pub by_symbology: BTreeMap<Option<Symbology>, u32>,
+    /// Reads that failed check digit validation.
+    pub invalid: u32,
+    /// Scans dropped for exceeding the buffer limit.
+    pub overflows: u32,
+    /// Errors receiving from the port.
+    pub read_errors: u32,
+    /// Repeat reads dropped by the dedupe window.
+    pub dedupe_drops: u32,
+    since: Instant,
+    last_scan: Option<Instant>,
+}
+
+impl Default for ScanStats {
+    fn default() -> Self {
+        Self {
+            total_scans: 0,
+            by_symbology: BTreeMap::new(),
+            invalid: 0,
+            overflows: 0,
+            read_errors: 0,
+            dedupe_drops: 0,
+            since: Instant::now(),
+            last_scan: None,
+        }
+    }
+}
+
+impl ScanStats {
+    pub(super) fn record_scan(&mut self, symbology: Option<Symbology>, now: Instant) {
+        self.total_scans += 1;
+        *self.by_symbology.entry(symbology).or_default() += 1;
+        self.last_scan = Some(now);
+    }
+
+    /// Time since the last reported read, if there was one.
+    pub fn since_last_scan(&self) -> Option<Duration> {
+        self.last_scan.map(|at| at.elapsed())
+    }
+
+    /// Time the counters cover.
+    pub fn uptime(&self) -> Duration {
+        self.since.elapsed()
+    }
+}
@@ -0,0 +1,175 @@
+//! Barcode symbologies supported by the GM65 (datasheet 8.5, appendix B).
+
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, Result};
+
+/// A barcode symbology the GM65 can decode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Symbology {
+    Ean13,
+    Ean8,
+    UpcA,
+    UpcE0,
+    UpcE1,
+    Code128,
+    Code39,
+    Code93,
+    Codabar,
+    Qr,
+    Interleaved2of5,
+    Industrial2of5,
+    Matrix2of5,
+    Code11,
+    MsiPlessey,
+    Rss14,
+    RssLimited,
+    RssExpanded,
+    DataMatrix,
+    Pdf417,
+}
+
+impl Symbology {
+    pub const ALL: [Symbology; 20] = [
+        Symbology::Ean13,
+        Symbology::Ean8,
+        Symbology::UpcA,
+        Symbology::UpcE0,
+        Symbology::UpcE1,
+        Symbology::Code128,
+        Symbology::Code39,
+        Symbology::Code93,
+        Symbology::Codabar,
+        Symbology::Qr,
+        Symbology::Interleaved2of5,
+        Symbology::Industrial2of5,
+        Symbology::Matrix2of5,
+        Symbology::Code11,
+        Symbology::MsiPlessey,
+        Symbology::Rss14,
+        Symbology::RssLimited,
+        Symbology::RssExpanded,
+        Symbology::DataMatrix,
+        Symbology::Pdf417,
+    ];
+
+    /// First and last zone bits holding the per-symbology enable flags.
+    pub const ZONE_FIRST: u16 = 0x002E;
+    pub const ZONE_LAST: u16 = 0x0055;
+
+    /// Zone bit whose bit 0 enables reading this symbology.
+    pub fn zone(self) -> u16 {
+        match self {
+            Symbology::Ean13 => 0x002E,
+            Symbology::Ean8 => 0x002F,
+            Symbology::UpcA => 0x0030,
+            Symbology::UpcE0 => 0x0031,
+            Symbology::UpcE1 => 0x0032,
+            Symbology::Code128 => 0x0033,
+            Symbology::Code39 => 0x0036,
+            Symbology::Code93 => 0x0039,
+            Symbology::Codabar => 0x003C,
+            Symbology::Qr => 0x003F,
+            Symbology::Interleaved2of5 => 0x0040,
+            Symbology::Industrial2of5 => 0x0043,
+            Symbology::Matrix2of5 => 0x0046,
+            Symbology::Code11 => 0x0049,
+            Symbology::MsiPlessey => 0x004C,
+            Symbology::Rss14 => 0x004F,
+            Symbology::RssLimited => 0x0050,
+            Symbology::RssExpanded => 0x0051,
+            Symbology::DataMatrix => 0x0054,
+            Symbology::Pdf417 => 0x0055,
+        }
+    }
+
+    /// Default Code ID character prefixed to scans of this symbology
+    /// (appendix B, configurable in zone bits 0x0091..=0x00A4).
+    pub fn code_id(self) -> u8 {
+        match self {
+            Symbology::Ean13 | Symbology::Ean8 => b'd',
+            Symbology::UpcA | Symbology::UpcE0 | Symbology::UpcE1 => b'c',
+            Symbology::Code128 => b'j',
+            Symbology::Code39 => b'b',
+            Symbology::Code93 => b'i',
+            Symbology::Codabar => b'a',
+            Symbology::Qr => b'Q',
+            Symbology::Interleaved2of5 => b'e',
+            Symbology::Industrial2of5 => b'D',
+            Symbology::Matrix2of5 => b'v',
+            Symbology::Code11 => b'H',
+            Symbology::MsiPlessey => b'm',
+            Symbology::Rss14 | Symbology::RssLimited | Symbology::RssExpanded => b'R',
+            Symbology::DataMatrix => b'u',
+            Symbology::Pdf417 => b'r',
+        }
+    }
+
+    /// Decode a default Code ID character.
+    ///
+    /// Some Code IDs are shared: EAN-13/EAN-8 and the UPC variants are told
+    /// apart by the payload, and GS1 DataBar is reported as RSS-14 unless the
+    /// payload is too long for it. RSS Limited is never returned.
+    pub fn from_code_id(code_id: u8, payload: &[u8]) -> Option<Symbology> {
+        let sym = match code_id {
+            b'd' if payload.len() == 8 => Symbology::Ean8,
+            b'd' => Symbology::Ean13,
+            b'c' if payload.len() == 8 && payload[0] == b'1' => Symbology::UpcE1,
+            b'c' if payload.len() == 8 => Symbology::UpcE0,
+            b'c' => Symbology::UpcA,
+            b'R' if payload.len() > 16 => Symbology::RssExpanded,
+            b'R' => Symbology::Rss14,
+            _ => *Symbology::ALL.iter().find(|sym| sym.code_id() == code_id)?,
+        };
+        Some(sym)
+    }
+
+    /// Single bit identifying this symbology in a `u32` set.
+    pub fn mask(self) -> u32 {
+        1 << (self as u32)
+    }
+
+    /// Stable lowercase name used in HTTP parameters.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Symbology::Ean13 => "ean13",
+            Symbology::Ean8 => "ean8",
+            Symbology::UpcA => "upca",
+            Symbology::UpcE0 => "upce0",
+            Symbology::UpcE1 => "upce1",
+            Symbology::Code128 => "code128",
+            Symbology::Code39 => "code39",
+            Symbology::Code93 => "code93",
+            Symbology::Codabar => "codabar",
+            Symbology::Qr => "qr",
+            Symbology::Interleaved2of5 => "itf",
+            Symbology::Industrial2of5 => "industrial25",
+            Symbology::Matrix2of5 => "matrix25",
+            Symbology::Code11 => "code11",
+            Symbology::MsiPlessey => "msi",
+            Symbology::Rss14 => "rss14",
+            Symbology::RssLimited => "rss-limited",
+            Symbology::RssExpanded => "rss-expanded",
+            Symbology::DataMatrix => "datamatrix",
+            Symbology::Pdf417 => "pdf417",
+        }
+    }
+}
+
+impl fmt::Display for Symbology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Symbology {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match Symbology::ALL.iter().find(|sym| sym.as_str() == s) {
+            Some(sym) => Ok(*sym),
+            None => bail!("Unknown symbology: {s}"),
+        }
+    }
+}
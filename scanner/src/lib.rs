@@ -0,0 +1,8 @@
+//! The parts of the firmware that don't touch ESP-IDF: the GM65 barcode
+//! scanner driver and scan pipeline, and the scan rules engine.
+//!
+//! They live in their own crate so `cargo test` can run them on the
+//! development machine; the device crate supplies the UART, NVS and HTTP.
+
+pub mod barcode;
+pub mod rules;
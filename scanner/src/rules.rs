@@ -0,0 +1,503 @@
+//! Scan-to-action rules: matching and dispatch.
+//!
+//! A rule is one line of text:
+//!
+//! ```text
+//! <matcher> => <action>; <action>; ...
+//! ```
+//!
+//! Matchers are `any`, `prefix:<text>`, `regex:<pattern>` and
+//! `symbology:<name>`. Actions are `display:<template>`, `beep:<pattern>`,
+//! `webhook:<url>` and `set:<name>=<template>`. A literal `;` in an action is
+//! written `\;` and a literal `\` as `\\`.
+//!
+//! Templates substitute `{payload}`, `{symbology}`, `{0}`..`{9}` (regex
+//! groups; for `prefix:` rules `{1}` is the text after the prefix) and any
+//! variable set by an earlier `set:` action. In `display:` templates `|`
+//! starts a new line.
+//!
+//! Matching and template rendering happen here; the actions themselves are
+//! carried out by an [`ActionSink`].
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+use anyhow::{bail, Context, Result};
+use regex_lite::Regex;
+
+use crate::barcode::{Feedback, Symbology};
+
+/// Most rules kept; they are stored as one NVS string.
+pub const MAX_RULES: usize = 16;
+
+/// What a rule matches on.
+#[derive(Debug, Clone)]
+pub enum Matcher {
+    /// Every scan.
+    Any,
+    /// Payloads starting with this text.
+    Prefix(String),
+    /// Payloads matching this regex anywhere; anchor it with `^`/`$`.
+    Regex(Regex),
+    /// Scans in this symbology.
+    Symbology(Symbology),
+}
+
+impl Matcher {
+    /// Template groups for a matching scan: `{0}` is the matched text,
+    /// then any regex groups or the text after a prefix.
+    fn captures(&self, symbology: Option<Symbology>, payload: &str) -> Option<Vec<String>> {
+        match self {
+            Matcher::Any => Some(vec![payload.to_string()]),
+            Matcher::Prefix(prefix) => {
+                let rest = payload.strip_prefix(prefix.as_str())?;
+                Some(vec![payload.to_string(), rest.to_string()])
+            }
+            Matcher::Regex(regex) => {
+                let captures = regex.captures(payload)?;
+                Some(
+                    captures
+                        .iter()
+                        .map(|group| group.map_or("", |m| m.as_str()).to_string())
+                        .collect(),
+                )
+            }
+            Matcher::Symbology(sym) => (symbology == Some(*sym)).then(|| vec![payload.to_string()]),
+        }
+    }
+}
+
+impl fmt::Display for Matcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Matcher::Any => f.write_str("any"),
+            Matcher::Prefix(prefix) => write!(f, "prefix:{prefix}"),
+            Matcher::Regex(regex) => write!(f, "regex:{}", regex.as_str()),
+            Matcher::Symbology(sym) => write!(f, "symbology:{sym}"),
+        }
+    }
+}
+
+impl FromStr for Matcher {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, arg) = s.split_once(':').unwrap_or((s, ""));
+        match kind {
+            "any" if arg.is_empty() => Ok(Matcher::Any),
+            "prefix" if !arg.is_empty() => Ok(Matcher::Prefix(arg.to_string())),
+            "regex" => {
+                let regex = Regex::new(arg).with_context(|| format!("Invalid regex: {arg}"))?;
+                Ok(Matcher::Regex(regex))
+            }
+            "symbology" => Ok(Matcher::Symbology(arg.parse()?)),
+            _ => bail!("Unknown matcher: {s}"),
+        }
+    }
+}
+
+/// Something a rule does when it matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Action {
+    /// Show the template on the display, `|` separating lines.
+    Display(String),
+    /// Play a feedback pattern.
+    Beep(Feedback),
+    /// POST the scan as JSON to this URL.
+    Webhook(String),
+    /// Store the rendered template in a variable for later templates.
+    Set { name: String, template: String },
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Display(template) => write!(f, "display:{}", escape(template)),
+            Action::Beep(feedback) => write!(f, "beep:{}", feedback.as_str()),
+            Action::Webhook(url) => write!(f, "webhook:{}", escape(url)),
+            Action::Set { name, template } => write!(f, "set:{}={}", name, escape(template)),
+        }
+    }
+}
+
+impl FromStr for Action {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((kind, arg)) = s.split_once(':') else {
+            bail!("Unknown action: {s}");
+        };
+        match kind {
+            "display" => Ok(Action::Display(arg.to_string())),
+            "beep" => Ok(Action::Beep(arg.parse()?)),
+            "webhook" => {
+                if !arg.starts_with("http://") && !arg.starts_with("https://") {
+                    bail!("Webhook URL must start with http:// or https://: {arg}");
+                }
+                Ok(Action::Webhook(arg.to_string()))
+            }
+            "set" => {
+                let Some((name, template)) = arg.split_once('=') else {
+                    bail!("Expected set:<name>=<template>: {s}");
+                };
+                let builtin = matches!(name, "payload" | "symbology")
+                    || name.bytes().all(|b| b.is_ascii_digit());
+                let valid = name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_');
+                if name.is_empty() || builtin || !valid {
+                    bail!("Invalid variable name: {name}");
+                }
+                Ok(Action::Set {
+                    name: name.to_string(),
+                    template: template.to_string(),
+                })
+            }
+            _ => bail!("Unknown action: {s}"),
+        }
+    }
+}
+
+/// A matcher and the actions to run when it matches.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    pub matcher: Matcher,
+    pub actions: Vec<Action>,
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} =>", self.matcher)?;
+        for (i, action) in self.actions.iter().enumerate() {
+            let sep = if i == 0 { "" } else { ";" };
+            write!(f, "{sep} {action}")?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Rule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let Some((matcher, actions)) = s.split_once("=>") else {
+            bail!("Expected <matcher> => <actions>: {s}");
+        };
+        let matcher = matcher.trim().parse()?;
+        let actions = split_actions(actions)
+            .iter()
+            .map(|action| action.trim())
+            .filter(|action| !action.is_empty())
+            .map(str::parse)
+            .collect::<Result<Vec<Action>>>()?;
+        if actions.is_empty() {
+            bail!("Rule has no actions: {s}");
+        }
+        Ok(Rule { matcher, actions })
+    }
+}
+
+/// Carries out rule actions on the device.
+pub trait ActionSink {
+    /// Show `lines` from the top of the display.
+    fn display(&mut self, lines: Vec<String>);
+    /// Play a beep/LED pattern.
+    fn beep(&mut self, feedback: Feedback);
+    /// POST the scan to `url`.
+    fn webhook(&mut self, url: &str, symbology: Option<Symbology>, payload: &str);
+}
+
+/// An ordered rule table plus the variables set by its actions.
+#[derive(Debug, Default)]
+pub struct RuleEngine {
+    rules: Vec<Rule>,
+    variables: BTreeMap<String, String>,
+}
+
+impl RuleEngine {
+    /// Parse a rule table, one rule per line. Blank lines and lines starting
+    /// with `#` are skipped.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut engine = RuleEngine::default();
+        for (i, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let rule = line
+                .parse()
+                .with_context(|| format!("Rule on line {}", i + 1))?;
+            engine.add(rule)?;
+        }
+        Ok(engine)
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    pub fn variables(&self) -> &BTreeMap<String, String> {
+        &self.variables
+    }
+
+    /// Append a rule; it runs only if no earlier rule matches.
+    pub fn add(&mut self, rule: Rule) -> Result<()> {
+        if self.rules.len() >= MAX_RULES {
+            bail!("At most {MAX_RULES} rules are supported");
+        }
+        self.rules.push(rule);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Rule> {
+        (index < self.rules.len()).then(|| self.rules.remove(index))
+    }
+
+    /// Run the actions of the first rule matching the scan. Returns that
+    /// rule's index, or `None` if no rule matched.
+    pub fn dispatch<S: ActionSink>(
+        &mut self,
+        symbology: Option<Symbology>,
+        payload: &str,
+        sink: &mut S,
+    ) -> Option<usize> {
+        let (index, rule, groups) = self.rules.iter().enumerate().find_map(|(i, rule)| {
+            let groups = rule.matcher.captures(symbology, payload)?;
+            Some((i, rule, groups))
+        })?;
+
+        let scan = Scan {
+            symbology,
+            payload,
+            groups: &groups,
+        };
+        for action in &rule.actions {
+            match action {
+                Action::Display(template) => {
+                    let lines = template
+                        .split('|')
+                        .map(|line| scan.render(line, &self.variables))
+                        .collect();
+                    sink.display(lines);
+                }
+                Action::Beep(feedback) => sink.beep(*feedback),
+                Action::Webhook(url) => sink.webhook(url, symbology, payload),
+                Action::Set { name, template } => {
+                    let value = scan.render(template, &self.variables);
+                    self.variables.insert(name.clone(), value);
+                }
+            }
+        }
+        Some(index)
+    }
+}
+
+impl fmt::Display for RuleEngine {
+    /// The rule table in the form [`RuleEngine::parse`] reads.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for rule in &self.rules {
+            writeln!(f, "{rule}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The scan a rule matched, for template rendering.
+struct Scan<'a> {
+    symbology: Option<Symbology>,
+    payload: &'a str,
+    groups: &'a [String],
+}
+
+impl Scan<'_> {
+    /// Substitute `{name}` placeholders; unknown names render empty and a
+    /// `{` without a closing `}` is kept as is.
+    fn render(&self, template: &str, variables: &BTreeMap<String, String>) -> String {
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else {
+                break;
+            };
+            out.push_str(&rest[..start]);
+            let name = &rest[start + 1..start + len];
+            match name {
+                "payload" => out.push_str(self.payload),
+                "symbology" => out.push_str(self.symbology.map_or("unknown", |sym| sym.as_str())),
+                _ => {
+                    let value = match name.parse::<usize>() {
+                        Ok(group) => self.groups.get(group),
+                        Err(_) => variables.get(name),
+                    };
+                    out.push_str(value.map_or("", String::as_str));
+                }
+            }
+            rest = &rest[start + len + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// Split an action list on `;`, treating `\;` as a literal semicolon and
+/// `\\` as a literal backslash. Any other backslash is kept as is.
+fn split_actions(s: &str) -> Vec<String> {
+    let mut actions = vec![String::new()];
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if matches!(chars.peek(), Some(';' | '\\')) => {
+                let escaped = chars.next().unwrap();
+                actions.last_mut().unwrap().push(escaped);
+            }
+            ';' => actions.push(String::new()),
+            c => actions.last_mut().unwrap().push(c),
+        }
+    }
+    actions
+}
+
+/// Escape an action argument so [`split_actions`] reads it back unchanged.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace(';', "\\;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Records what the rules asked for.
+    #[derive(Default)]
+    struct Recorder {
+        displayed: Vec<Vec<String>>,
+        beeps: Vec<Feedback>,
+        webhooks: Vec<String>,
+    }
+
+    impl ActionSink for Recorder {
+        fn display(&mut self, lines: Vec<String>) {
+            self.displayed.push(lines);
+        }
+
+        fn beep(&mut self, feedback: Feedback) {
+            self.beeps.push(feedback);
+        }
+
+        fn webhook(&mut self, url: &str, _symbology: Option<Symbology>, payload: &str) {
+            self.webhooks.push(format!("{url} {payload}"));
+        }
+    }
+
+    #[test]
+    fn first_matching_rule_runs() {
+        let mut engine = RuleEngine::parse(
+            "# comment\n\
+             \n\
+             prefix:LOC: => display:Location|{1}; beep:success\n\
+             symbology:qr => webhook:https://example.com/scan\n\
+             any => beep:error\n",
+        )
+        .unwrap();
+        let mut sink = Recorder::default();
+
+        let matched = engine.dispatch(Some(Symbology::Qr), "LOC:A-12", &mut sink);
+        assert_eq!(matched, Some(0));
+        assert_eq!(sink.displayed, [["Location", "A-12"]]);
+        assert_eq!(sink.beeps, [Feedback::Success]);
+        assert!(sink.webhooks.is_empty());
+
+        assert_eq!(
+            engine.dispatch(Some(Symbology::Qr), "X", &mut sink),
+            Some(1)
+        );
+        assert_eq!(sink.webhooks, ["https://example.com/scan X"]);
+
+        assert_eq!(engine.dispatch(None, "X", &mut sink), Some(2));
+        assert_eq!(sink.beeps, [Feedback::Success, Feedback::Error]);
+    }
+
+    #[test]
+    fn unmatched_scans_run_nothing() {
+        let mut engine = RuleEngine::parse("prefix:LOC: => beep:success").unwrap();
+        let mut sink = Recorder::default();
+        assert_eq!(engine.dispatch(None, "ITEM", &mut sink), None);
+        assert!(sink.beeps.is_empty());
+    }
+
+    #[test]
+    fn templates_use_groups_and_variables() {
+        let mut engine = RuleEngine::parse(
+            r"prefix:LOC: => set:loc={1}
+              regex:^(\d{3})-(\d+)$ => display:{loc}|{1} / {2}|{symbology} {unknown}{",
+        )
+        .unwrap();
+        let mut sink = Recorder::default();
+
+        engine.dispatch(None, "LOC:Shelf 4", &mut sink);
+        assert_eq!(engine.variables()["loc"], "Shelf 4");
+        engine.dispatch(Some(Symbology::Code128), "123-45", &mut sink);
+        assert_eq!(sink.displayed, [["Shelf 4", "123 / 45", "code128 {"]]);
+    }
+
+    #[test]
+    fn escapes_round_trip_through_save_and_load() {
+        let text = r"any => display:a\;b|c:\\dir\\; set:path=C:\\x\\; webhook:http://h/?a=1\;b=2";
+        let engine = RuleEngine::parse(text).unwrap();
+        assert_eq!(
+            engine.rules()[0].actions,
+            [
+                Action::Display(r"a;b|c:\dir\".into()),
+                Action::Set {
+                    name: "path".into(),
+                    template: r"C:\x\".into(),
+                },
+                Action::Webhook("http://h/?a=1;b=2".into()),
+            ]
+        );
+
+        let saved = engine.to_string();
+        let reloaded = RuleEngine::parse(&saved).unwrap();
+        assert_eq!(reloaded.rules()[0].actions, engine.rules()[0].actions);
+        assert_eq!(reloaded.to_string(), saved);
+    }
+
+    #[test]
+    fn lone_backslashes_are_kept() {
+        let rule: Rule = r"any => display:a\b".parse().unwrap();
+        assert_eq!(rule.actions, [Action::Display(r"a\b".into())]);
+    }
+
+    #[test]
+    fn rejects_invalid_rules() {
+        for line in [
+            "any",
+            "any =>",
+            "nothing => beep:success",
+            "prefix: => beep:success",
+            "regex:( => beep:success",
+            "symbology:nope => beep:success",
+            "any => shout:hi",
+            "any => beep:nope",
+            "any => webhook:ftp://example.com",
+            "any => set:payload=x",
+            "any => set:3=x",
+            "any => set:a-b=x",
+        ] {
+            assert!(line.parse::<Rule>().is_err(), "{line}");
+        }
+
+        let error = RuleEngine::parse("any => beep:success\nany").unwrap_err();
+        assert_eq!(error.to_string(), "Rule on line 2");
+    }
+
+    #[test]
+    fn limits_the_table_size() {
+        let mut engine = RuleEngine::default();
+        for _ in 0..MAX_RULES {
+            engine.add("any => beep:success".parse().unwrap()).unwrap();
+        }
+        assert!(engine.add("any => beep:error".parse().unwrap()).is_err());
+        assert!(engine.remove(MAX_RULES).is_none());
+        assert!(engine.remove(0).is_some());
+        assert_eq!(engine.rules().len(), MAX_RULES - 1);
+    }
+}
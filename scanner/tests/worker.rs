@@ -0,0 +1,194 @@
+//! The barcode worker driven end to end over a simulated GM65.
+
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+use scanner::barcode::mock::{MockPin, MockScanner};
+use scanner::barcode::{
+    BarcodeEvent, BarcodeHandle, BarcodeScanner, BaudRate, Framing, ScanMode, ScanResult, Symbology,
+};
+
+const EVENT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Worker running on `scanner`, started and connected. It may still be
+/// configuring the scanner, so scans replayed at once also check that bytes
+/// arriving during a command aren't lost.
+struct Worker {
+    _scanner: BarcodeScanner,
+    handle: BarcodeHandle,
+    events: Receiver<BarcodeEvent>,
+}
+
+impl Worker {
+    fn start(scanner: &MockScanner) -> Self {
+        let (tx, events) = mpsc::channel();
+        let worker = BarcodeScanner::start(
+            scanner.clone(),
+            None::<MockPin>,
+            None::<MockPin>,
+            None::<MockPin>,
+            move |event| {
+                tx.send(event).ok();
+            },
+        )
+        .unwrap();
+        let worker = Worker {
+            handle: worker.handle(),
+            _scanner: worker,
+            events,
+        };
+        worker.expect(Duration::from_secs(5), |event| {
+            matches!(event, BarcodeEvent::Connected)
+        });
+        worker
+    }
+
+    /// Wait for the next event and check it.
+    fn expect(&self, timeout: Duration, check: impl Fn(&BarcodeEvent) -> bool) -> BarcodeEvent {
+        let event = self.events.recv_timeout(timeout).expect("no event");
+        assert!(check(&event), "unexpected event {event:?}");
+        event
+    }
+
+    fn next_scan(&self) -> ScanResult {
+        match self.expect(EVENT_TIMEOUT, |event| {
+            matches!(event, BarcodeEvent::Scanned(_))
+        }) {
+            BarcodeEvent::Scanned(scan) => scan,
+            _ => unreachable!(),
+        }
+    }
+
+    fn assert_quiet(&self) {
+        let event = self.events.recv_timeout(Duration::from_millis(300));
+        assert!(event.is_err(), "unexpected event {event:?}");
+    }
+}
+
+/// Put the simulated scanner's stored baud rate at `baud`.
+fn set_scanner_baud(scanner: &MockScanner, baud: BaudRate) {
+    let [low, high] = baud.zone_bytes();
+    scanner.set_zone(0x002A, low);
+    scanner.set_zone(0x002B, high);
+}
+
+#[test]
+fn finds_the_scanner_at_a_non_default_rate() {
+    let scanner = MockScanner::new();
+    set_scanner_baud(&scanner, BaudRate::B57600);
+
+    let worker = Worker::start(&scanner);
+    assert!(worker.handle.is_connected());
+    assert_eq!(worker.handle.baud_rate().unwrap(), BaudRate::B57600);
+    // Code ID output is turned on so scans carry their symbology.
+    assert_eq!(scanner.zone(0x0060) & 0x04, 0x04);
+}
+
+#[test]
+fn switches_baud_rate_and_keeps_reading() {
+    let scanner = MockScanner::new();
+    let worker = Worker::start(&scanner);
+
+    worker.handle.set_baud_rate(BaudRate::B115200).unwrap();
+    assert_eq!(worker.handle.baud_rate().unwrap(), BaudRate::B115200);
+    assert_eq!(
+        [scanner.zone(0x002A), scanner.zone(0x002B)],
+        BaudRate::B115200.zone_bytes()
+    );
+
+    scanner.replay(b"QHello\r\n");
+    let scan = worker.next_scan();
+    assert_eq!(scan.symbology, Some(Symbology::Qr));
+    assert_eq!(scan.payload, "Hello");
+}
+
+#[test]
+fn splits_terminated_scans() {
+    let scanner = MockScanner::new();
+    let worker = Worker::start(&scanner);
+
+    scanner.replay(b"QFIRST\r\nbSECOND\r\n");
+    assert_eq!(worker.next_scan().payload, "FIRST");
+    let second = worker.next_scan();
+    assert_eq!(second.symbology, Some(Symbology::Code39));
+    assert_eq!(second.payload, "SECOND");
+}
+
+#[test]
+fn ends_scans_on_an_idle_gap() {
+    let scanner = MockScanner::new();
+    let worker = Worker::start(&scanner);
+
+    worker
+        .handle
+        .set_framing(Framing::IdleGap(Duration::from_millis(50)))
+        .unwrap();
+    scanner.replay(b"QNO-TAIL");
+    assert_eq!(worker.next_scan().payload, "NO-TAIL");
+}
+
+#[test]
+fn reports_oversized_scans() {
+    let scanner = MockScanner::new();
+    let worker = Worker::start(&scanner);
+
+    worker.handle.set_max_scan_len(8).unwrap();
+    scanner.replay(b"QTOO-LONG-FOR-THE-BUFFER\r\nQSHORT\r\n");
+    worker.expect(
+        EVENT_TIMEOUT,
+        |event| matches!(event, BarcodeEvent::Error(e) if e == "Barcode too long"),
+    );
+    assert_eq!(worker.next_scan().payload, "SHORT");
+    assert_eq!(worker.handle.stats().overflows, 1);
+}
+
+#[test]
+fn drops_repeat_scans_within_the_window() {
+    let scanner = MockScanner::new();
+    let worker = Worker::start(&scanner);
+
+    worker
+        .handle
+        .set_dedupe_window(Duration::from_secs(5))
+        .unwrap();
+    scanner.replay(b"QSAME\r\n");
+    assert_eq!(worker.next_scan().payload, "SAME");
+    scanner.replay(b"QSAME\r\n");
+    worker.assert_quiet();
+
+    // The same text in another symbology is a different label.
+    scanner.replay(b"bSAME\r\n");
+    assert_eq!(worker.next_scan().symbology, Some(Symbology::Code39));
+
+    let stats = worker.handle.stats();
+    assert_eq!(stats.total_scans, 2);
+    assert_eq!(stats.dedupe_drops, 1);
+}
+
+#[test]
+fn reconnects_after_power_loss_and_restores_settings() {
+    let scanner = MockScanner::new();
+    let worker = Worker::start(&scanner);
+
+    worker.handle.set_scan_mode(ScanMode::Continuous).unwrap();
+    worker.handle.set_baud_rate(BaudRate::B115200).unwrap();
+
+    // Two missed health probes, five seconds apart, report a disconnect.
+    scanner.set_powered(false);
+    worker.expect(Duration::from_secs(15), |event| {
+        matches!(event, BarcodeEvent::Disconnected)
+    });
+    assert!(!worker.handle.is_connected());
+
+    // The scanner powers up at its saved settings: 9600 baud, manual mode.
+    scanner.set_powered(true);
+    worker.expect(Duration::from_secs(10), |event| {
+        matches!(event, BarcodeEvent::Connected)
+    });
+    assert!(worker.handle.is_connected());
+    assert_eq!(worker.handle.scan_mode().unwrap(), ScanMode::Continuous);
+    assert_eq!(worker.handle.baud_rate().unwrap(), BaudRate::B115200);
+
+    scanner.replay(b"QBACK\r\n");
+    assert_eq!(worker.next_scan().payload, "BACK");
+}
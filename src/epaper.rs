@@ -37,6 +37,8 @@ pub enum DisplayJob {
     Clear,
     ShowText(String),
     UpdateLine { line_number: u8, text: String },
+    // Replace consecutive lines starting at `first_line` with one refresh.
+    UpdateLines { first_line: u8, lines: Vec<String> },
 }
 
 #[derive(Clone)]
@@ -186,29 +188,7 @@ where
                     .context("Set base buffer failed after ShowText")?;
             }
             DisplayJob::UpdateLine { line_number, text } => {
-                use embedded_graphics::primitives::Rectangle;
-
-                // Calculate line position
-                let line_height = 12;
-                let y_offset = 10 + (line_number as i32 * line_height);
-
-                // Clear only the specific line region (local update)
-                let clear_rect = Rectangle::new(
-                    Point::new(0, y_offset - 2),
-                    Size::new(122, line_height as u32), // Full width, one line height
-                );
-
-                framebuffer.fill_solid(&clear_rect, Color::White).ok();
-
-                // Draw new text at the line position
-                let style = MonoTextStyleBuilder::new()
-                    .font(&FONT_6X10)
-                    .text_color(Color::Black)
-                    .build();
-
-                Text::new(&text, Point::new(10, y_offset), style)
-                    .draw(&mut framebuffer)
-                    .ok();
+                draw_line(&mut framebuffer, line_number, &text);
 
                 // Quick partial refresh - only updates changed pixels
                 epd.update_and_display_frame(&mut spi, framebuffer.buffer(), delay)
@@ -218,6 +198,48 @@ where
                 epd.set_partial_base_buffer(&mut spi, delay, framebuffer.buffer())
                     .context("Set base buffer failed after UpdateLine")?;
             }
+            DisplayJob::UpdateLines { first_line, lines } => {
+                for (offset, text) in lines.iter().enumerate() {
+                    draw_line(
+                        &mut framebuffer,
+                        first_line.saturating_add(offset as u8),
+                        text,
+                    );
+                }
+
+                epd.update_and_display_frame(&mut spi, framebuffer.buffer(), delay)
+                    .context("Partial update failed")?;
+
+                epd.set_partial_base_buffer(&mut spi, delay, framebuffer.buffer())
+                    .context("Set base buffer failed after UpdateLines")?;
+            }
         }
     }
 }
+
+/// Clear one text line in the framebuffer and draw `text` on it.
+fn draw_line(framebuffer: &mut Display2in13, line_number: u8, text: &str) {
+    use embedded_graphics::primitives::Rectangle;
+
+    // Calculate line position
+    let line_height = 12;
+    let y_offset = 10 + (line_number as i32 * line_height);
+
+    // Clear only the specific line region (local update)
+    let clear_rect = Rectangle::new(
+        Point::new(0, y_offset - 2),
+        Size::new(122, line_height as u32), // Full width, one line height
+    );
+
+    framebuffer.fill_solid(&clear_rect, Color::White).ok();
+
+    // Draw new text at the line position
+    let style = MonoTextStyleBuilder::new()
+        .font(&FONT_6X10)
+        .text_color(Color::Black)
+        .build();
+
+    Text::new(text, Point::new(10, y_offset), style)
+        .draw(framebuffer)
+        .ok();
+}
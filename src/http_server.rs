@@ -1,8 +1,31 @@
 use anyhow::Result;
-use embedded_svc::{http::Method, io::Write as _};
+use embedded_svc::{
+    http::{Headers as _, Method},
+    io::{Read as _, Write as _},
+};
 use esp_idf_svc::http::server::{Configuration as HttpConfig, EspHttpServer};
 use esp_idf_svc::nvs::EspDefaultNvs;
+use std::collections::BTreeMap;
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::barcode::{
+    BarcodeHandle, Feedback, Lighting, PayloadEncoding, ScanError, ScanMode, ScanStats, Symbology,
+};
+use crate::json::json_string;
+use crate::rules::{Rule, RuleEngine};
+use crate::scan_history::{self, HistoryEntry, ScanHistory};
+
+/// Largest scanner profile accepted for upload, in bytes.
+const MAX_PROFILE_LEN: usize = 8 * 1024;
+
+/// Longest read timeout or interval the scanner takes, in ms.
+const MAX_READ_TIMING_MS: u32 = 25_500;
+
+/// Longest `/barcode-scan` wait, in ms. The server handles one request at a
+/// time, so a longer wait would hold up every other page; clients wanting to
+/// wait longer poll again after a timeout.
+const MAX_SCAN_WAIT_MS: u64 = 3_000;
 
 #[derive(Clone, Debug)]
 pub struct BlinkConfig {
@@ -36,6 +59,108 @@ impl BlinkConfig {
     }
 }
 
+/// Persisted barcode scanner settings, re-applied at boot.
+///
+/// `None` fields have never been set from firmware and are left as the
+/// scanner powered up.
+#[derive(Clone, Debug)]
+pub struct ScannerConfig {
+    pub scan_mode: Option<ScanMode>,
+    pub encoding: Option<PayloadEncoding>,
+    /// Per-symbology enable overrides.
+    pub symbologies: BTreeMap<Symbology, bool>,
+    /// Repeat reads of a label within this many ms are dropped; 0 = off.
+    pub dedupe_window_ms: u32,
+    pub aim: Option<Lighting>,
+    pub illumination: Option<Lighting>,
+    /// Single-read timeout in ms, a multiple of 100; 0 = no limit.
+    pub read_timeout_ms: Option<u32>,
+    /// Pause before the same code is read again in ms, a multiple of 100.
+    pub read_interval_ms: Option<u32>,
+}
+
+impl ScannerConfig {
+    pub fn load(nvs: &EspDefaultNvs) -> Self {
+        let scan_mode = nvs
+            .get_u8("scan_mode")
+            .ok()
+            .flatten()
+            .map(ScanMode::from_bits);
+
+        let encoding = nvs
+            .get_u8("encoding")
+            .ok()
+            .flatten()
+            .map(PayloadEncoding::from_bits);
+
+        // Two bitmasks indexed by `Symbology::mask`: which symbologies have
+        // an override, and whether that override enables them.
+        let sym_set = nvs.get_u32("sym_set").ok().flatten().unwrap_or(0);
+        let sym_on = nvs.get_u32("sym_on").ok().flatten().unwrap_or(0);
+        let symbologies = Symbology::ALL
+            .into_iter()
+            .filter(|sym| sym_set & sym.mask() != 0)
+            .map(|sym| (sym, sym_on & sym.mask() != 0))
+            .collect();
+
+        let dedupe_window_ms = nvs.get_u32("dedupe_ms").ok().flatten().unwrap_or(0);
+
+        let aim = nvs.get_u8("aim").ok().flatten().map(Lighting::from_bits);
+        let illumination = nvs
+            .get_u8("illumination")
+            .ok()
+            .flatten()
+            .map(Lighting::from_bits);
+        let read_timeout_ms = nvs.get_u32("read_timeout_ms").ok().flatten();
+        let read_interval_ms = nvs.get_u32("read_intvl_ms").ok().flatten();
+
+        ScannerConfig {
+            scan_mode,
+            encoding,
+            symbologies,
+            dedupe_window_ms,
+            aim,
+            illumination,
+            read_timeout_ms,
+            read_interval_ms,
+        }
+    }
+
+    pub fn save(&self, nvs: &EspDefaultNvs) -> Result<()> {
+        if let Some(mode) = self.scan_mode {
+            nvs.set_u8("scan_mode", mode.bits())?;
+        }
+        if let Some(encoding) = self.encoding {
+            nvs.set_u8("encoding", encoding.bits())?;
+        }
+
+        let mut sym_set = 0;
+        let mut sym_on = 0;
+        for (sym, enabled) in &self.symbologies {
+            sym_set |= sym.mask();
+            if *enabled {
+                sym_on |= sym.mask();
+            }
+        }
+        nvs.set_u32("sym_set", sym_set)?;
+        nvs.set_u32("sym_on", sym_on)?;
+        nvs.set_u32("dedupe_ms", self.dedupe_window_ms)?;
+        if let Some(aim) = self.aim {
+            nvs.set_u8("aim", aim.bits())?;
+        }
+        if let Some(illumination) = self.illumination {
+            nvs.set_u8("illumination", illumination.bits())?;
+        }
+        if let Some(timeout_ms) = self.read_timeout_ms {
+            nvs.set_u32("read_timeout_ms", timeout_ms)?;
+        }
+        if let Some(interval_ms) = self.read_interval_ms {
+            nvs.set_u32("read_intvl_ms", interval_ms)?;
+        }
+        Ok(())
+    }
+}
+
 /// Events emitted by the HTTP server
 #[derive(Debug, Clone)]
 pub enum ServerEvent {
@@ -45,6 +170,15 @@ pub enum ServerEvent {
     BarcodeTrigger(bool),
     BarcodeLed(bool),
     BarcodeBeep(bool),
+    BarcodeScanMode(ScanMode),
+    BarcodeEncoding(PayloadEncoding),
+    BarcodeSymbology { symbology: Symbology, enabled: bool },
+    BarcodeDedupeWindow(Duration),
+    BarcodeAim(Lighting),
+    BarcodeIllumination(Lighting),
+    BarcodeReadTimeout(Duration),
+    BarcodeReadInterval(Duration),
+    BarcodeFeedback(Feedback),
 }
 
 pub struct HttpServer {
@@ -52,24 +186,146 @@ pub struct HttpServer {
 }
 
 impl HttpServer {
-    pub fn start<F>(config: BlinkConfig, nvs: EspDefaultNvs, on_event: F) -> Result<Self>
+    pub fn start<F>(
+        config: BlinkConfig,
+        scanner_config: ScannerConfig,
+        barcode: BarcodeHandle,
+        history: Arc<Mutex<ScanHistory>>,
+        rules: Arc<Mutex<RuleEngine>>,
+        nvs: EspDefaultNvs,
+        on_event: F,
+    ) -> Result<Self>
     where
         F: FnMut(ServerEvent) + Send + 'static,
     {
         let mut server = EspHttpServer::new(&HttpConfig::default())?;
 
         let blink_cfg = Arc::new(Mutex::new(config));
+        let scanner_cfg = Arc::new(Mutex::new(scanner_config));
         let nvs_handle = Arc::new(Mutex::new(nvs));
         let event_callback = Arc::new(Mutex::new(on_event));
 
         // Root route: show form
         {
             let blink_cfg = blink_cfg.clone();
+            let scanner_cfg = scanner_cfg.clone();
+            let barcode = barcode.clone();
+            let rules = rules.clone();
             server.fn_handler::<anyhow::Error, _>("/", Method::Get, move |req| {
                 let mut resp = req.into_ok_response()?;
 
                 let cfg = blink_cfg.lock().unwrap();
                 let enabled_str = if cfg.enabled { "checked" } else { "" };
+                let scanner_state = if barcode.is_connected() {
+                    "connected"
+                } else {
+                    "disconnected"
+                };
+                let scanner = scanner_cfg.lock().unwrap();
+                let scan_mode_options = [
+                    ScanMode::Manual,
+                    ScanMode::Command,
+                    ScanMode::Continuous,
+                    ScanMode::Sensor,
+                ]
+                .iter()
+                .map(|mode| {
+                    let selected = if scanner.scan_mode == Some(*mode) {
+                        " selected"
+                    } else {
+                        ""
+                    };
+                    format!(
+                        r#"<option value="{0}"{1}>{0}</option>"#,
+                        mode.as_str(),
+                        selected
+                    )
+                })
+                .collect::<String>();
+                let encoding_options = [
+                    PayloadEncoding::Gbk,
+                    PayloadEncoding::Unicode,
+                    PayloadEncoding::Big5,
+                ]
+                .iter()
+                .map(|encoding| {
+                    let selected = if scanner.encoding == Some(*encoding) {
+                        " selected"
+                    } else {
+                        ""
+                    };
+                    format!(
+                        r#"<option value="{0}"{1}>{0}</option>"#,
+                        encoding.as_str(),
+                        selected
+                    )
+                })
+                .collect::<String>();
+                let lighting_options = |current: Option<Lighting>| {
+                    [Lighting::Off, Lighting::Standard, Lighting::AlwaysOn]
+                        .iter()
+                        .map(|mode| {
+                            let selected = if current == Some(*mode) {
+                                " selected"
+                            } else {
+                                ""
+                            };
+                            format!(
+                                r#"<option value="{0}"{1}>{0}</option>"#,
+                                mode.as_str(),
+                                selected
+                            )
+                        })
+                        .collect::<String>()
+                };
+                let aim_options = lighting_options(scanner.aim);
+                let illumination_options = lighting_options(scanner.illumination);
+                let symbology_options = Symbology::ALL
+                    .iter()
+                    .map(|sym| {
+                        let state = match scanner.symbologies.get(sym) {
+                            Some(true) => " (on)",
+                            Some(false) => " (off)",
+                            None => "",
+                        };
+                        format!(
+                            r#"<option value="{0}">{0}{1}</option>"#,
+                            sym.as_str(),
+                            state
+                        )
+                    })
+                    .collect::<String>();
+                let feedback_options = [
+                    Feedback::Success,
+                    Feedback::Error,
+                    Feedback::Warning,
+                    Feedback::Flash(0),
+                ]
+                .iter()
+                .map(|feedback| format!(r#"<option value="{0}">{0}</option>"#, feedback.as_str()))
+                .collect::<String>();
+                let (rule_items, variables) = {
+                    let rules = rules.lock().unwrap();
+                    let rule_items = rules
+                        .rules()
+                        .iter()
+                        .enumerate()
+                        .map(|(i, rule)| {
+                            format!(
+                                r#"<li><code>{}</code> <a href="/rules-delete?index={}">delete</a></li>"#,
+                                html_escape(&rule.to_string()),
+                                i
+                            )
+                        })
+                        .collect::<String>();
+                    let variables = rules
+                        .variables()
+                        .iter()
+                        .map(|(name, value)| format!("{} = {}", name, value))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    (rule_items, html_escape(&variables))
+                };
                 let html = format!(
                     r#"<!doctype html>
 <html>
@@ -122,6 +378,12 @@ impl HttpServer {
     </form>
 
     <h2>Barcode Scanner Controls</h2>
+    <p>Scanner: {scanner_state}</p>
+    <form action="/barcode-scan" method="GET">
+      <input type="hidden" name="timeout_ms" value="3000">
+      <button type="submit">Scan Once (wait up to 3 s)</button>
+    </form>
+    <br>
     <form action="/barcode-trigger" method="GET">
       <button type="submit" name="action" value="on">Trigger ON (Start Scan)</button>
       <button type="submit" name="action" value="off">Trigger OFF (Stop Scan)</button>
@@ -136,12 +398,136 @@ impl HttpServer {
       <button type="submit" name="action" value="on">Beep ON</button>
       <button type="submit" name="action" value="off">Beep OFF</button>
     </form>
+    <br>
+    <p>
+      Scan history:
+      <a href="/history">latest</a> |
+      <a href="/history/export?format=csv">CSV</a> |
+      <a href="/history/export?format=json">JSON</a>
+    </p>
+    <p>
+      Scan statistics:
+      <a href="/barcode-stats">view</a> |
+      <a href="/barcode-stats-reset">reset</a>
+    </p>
+    <form action="/barcode-feedback" method="GET">
+      <label>
+        Feedback:
+        <select name="pattern">{feedback_options}</select>
+      </label>
+      <label>
+        Flashes:
+        <input type="number" name="count" min="1" max="10" value="3">
+      </label>
+      <button type="submit">Play</button>
+    </form>
+    <br>
+    <form action="/barcode-scan-mode" method="GET">
+      <label>
+        Scan mode:
+        <select name="mode">{scan_mode_options}</select>
+      </label>
+      <button type="submit">Apply</button>
+    </form>
+    <br>
+    <form action="/barcode-encoding" method="GET">
+      <label>
+        Text encoding:
+        <select name="encoding">{encoding_options}</select>
+      </label>
+      <button type="submit">Apply</button>
+    </form>
+    <br>
+    <form action="/barcode-symbology" method="GET">
+      <label>
+        Symbology:
+        <select name="sym">{symbology_options}</select>
+      </label>
+      <button type="submit" name="action" value="on">Enable</button>
+      <button type="submit" name="action" value="off">Disable</button>
+    </form>
+    <br>
+    <form action="/barcode-dedupe" method="GET">
+      <label>
+        Ignore repeat scans within (ms, 0 = off):
+        <input type="number" name="window_ms" min="0" max="60000" value="{dedupe_window_ms}">
+      </label>
+      <button type="submit">Apply</button>
+    </form>
+    <br>
+    <form action="/barcode-aim" method="GET">
+      <label>
+        Aiming light:
+        <select name="mode">{aim_options}</select>
+      </label>
+      <button type="submit">Apply</button>
+    </form>
+    <br>
+    <form action="/barcode-illumination" method="GET">
+      <label>
+        Illumination:
+        <select name="mode">{illumination_options}</select>
+      </label>
+      <button type="submit">Apply</button>
+    </form>
+    <br>
+    <form action="/barcode-timing" method="GET">
+      <label>
+        Read timeout (ms, 0 = no limit):
+        <input type="number" name="timeout_ms" min="0" max="25500" step="100" value="{read_timeout_ms}">
+      </label>
+      <label>
+        Read interval (ms):
+        <input type="number" name="interval_ms" min="0" max="25500" step="100" value="{read_interval_ms}">
+      </label>
+      <button type="submit">Apply</button>
+    </form>
+
+    <h2>Scanner Profile</h2>
+    <p>
+      <a href="/scanner-profile">Download current settings</a> |
+      <a href="/scanner-save">Save settings to scanner flash</a>
+    </p>
+    <form action="/scanner-profile" method="POST">
+      <textarea name="profile" rows="6" cols="60" placeholder='{{"tail": "crlf", "enabled": ["qr", "ean13"]}}'></textarea>
+      <br>
+      <label>
+        <input type="checkbox" name="save" value="1">
+        Save to scanner flash
+      </label>
+      <button type="submit">Apply Profile</button>
+    </form>
+
+    <h2>Scan Rules</h2>
+    <p>The first matching rule runs; unmatched scans are shown as usual.</p>
+    <ol start="0">{rule_items}</ol>
+    <p>Variables: {variables}</p>
+    <form action="/rules-add" method="GET">
+      <label>
+        Rule:
+        <input type="text" name="rule" size="60" placeholder="prefix:LOC: => set:loc={{1}}; beep:success">
+      </label>
+      <button type="submit">Add</button>
+    </form>
   </body>
 </html>
 "#,
                     period_ms = cfg.period_ms,
                     enabled = cfg.enabled,
                     enabled_checked = enabled_str,
+                    scanner_state = scanner_state,
+                    feedback_options = feedback_options,
+                    scan_mode_options = scan_mode_options,
+                    encoding_options = encoding_options,
+                    symbology_options = symbology_options,
+                    dedupe_window_ms = scanner.dedupe_window_ms,
+                    aim_options = aim_options,
+                    illumination_options = illumination_options,
+                    // Module defaults until set from here.
+                    read_timeout_ms = scanner.read_timeout_ms.unwrap_or(5000),
+                    read_interval_ms = scanner.read_interval_ms.unwrap_or(1000),
+                    rule_items = rule_items,
+                    variables = variables,
                 );
 
                 resp.write_all(html.as_bytes())?;
@@ -374,7 +760,7 @@ impl HttpServer {
 
         // /barcode-beep route: control scanner beep
         {
-            let event_cb = event_callback;
+            let event_cb = event_callback.clone();
 
             server.fn_handler::<anyhow::Error, _>("/barcode-beep", Method::Get, move |req| {
                 let uri = req.uri();
@@ -406,6 +792,805 @@ impl HttpServer {
             })?;
         }
 
+        // /barcode-feedback route: play a beep/LED pattern
+        {
+            let event_cb = event_callback.clone();
+
+            server.fn_handler::<anyhow::Error, _>(
+                "/barcode-feedback",
+                Method::Get,
+                move |req| {
+                    let uri = req.uri();
+                    let count = query_param(uri, "count").and_then(|v| v.parse::<u8>().ok());
+                    let feedback = match (query_param(uri, "pattern"), count) {
+                        (Some("flash"), Some(count)) => Some(Feedback::Flash(count.clamp(1, 10))),
+                        (Some(pattern), _) => pattern.parse().ok(),
+                        (None, _) => None,
+                    };
+
+                    if let Some(feedback) = feedback {
+                        log::info!("Received barcode feedback request: {:?}", feedback);
+
+                        if let Ok(mut callback) = event_cb.lock() {
+                            callback(ServerEvent::BarcodeFeedback(feedback));
+                        }
+                    }
+
+                    // Redirect back to root
+                    let mut resp = req.into_response(302, Some("Found"), &[("Location", "/")])?;
+                    resp.write_all(b"Redirecting...\n")?;
+                    Ok(())
+                },
+            )?;
+        }
+
+        // /barcode-scan-mode route: switch scan mode, persist to NVS, emit event
+        {
+            let scanner_cfg = scanner_cfg.clone();
+            let nvs_handle = nvs_handle.clone();
+            let event_cb = event_callback.clone();
+
+            server.fn_handler::<anyhow::Error, _>(
+                "/barcode-scan-mode",
+                Method::Get,
+                move |req| {
+                    if let Some(mode) = query_param(req.uri(), "mode").and_then(|v| v.parse().ok())
+                    {
+                        log::info!("Received barcode scan mode request: {:?}", mode);
+
+                        {
+                            let mut cfg = scanner_cfg.lock().unwrap();
+                            cfg.scan_mode = Some(mode);
+
+                            if let Ok(nvs) = nvs_handle.lock() {
+                                if let Err(e) = cfg.save(&nvs) {
+                                    log::warn!("Failed to save scanner config to NVS: {:?}", e);
+                                }
+                            }
+                        }
+
+                        if let Ok(mut callback) = event_cb.lock() {
+                            callback(ServerEvent::BarcodeScanMode(mode));
+                        }
+                    }
+
+                    // Redirect back to root
+                    let mut resp = req.into_response(302, Some("Found"), &[("Location", "/")])?;
+                    resp.write_all(b"Redirecting...\n")?;
+                    Ok(())
+                },
+            )?;
+        }
+
+        // /barcode-encoding route: switch payload encoding, persist to NVS, emit event
+        {
+            let scanner_cfg = scanner_cfg.clone();
+            let nvs_handle = nvs_handle.clone();
+            let event_cb = event_callback.clone();
+
+            server.fn_handler::<anyhow::Error, _>(
+                "/barcode-encoding",
+                Method::Get,
+                move |req| {
+                    let encoding = query_param(req.uri(), "encoding").and_then(|v| v.parse().ok());
+                    if let Some(encoding) = encoding {
+                        log::info!("Received barcode encoding request: {:?}", encoding);
+
+                        {
+                            let mut cfg = scanner_cfg.lock().unwrap();
+                            cfg.encoding = Some(encoding);
+
+                            if let Ok(nvs) = nvs_handle.lock() {
+                                if let Err(e) = cfg.save(&nvs) {
+                                    log::warn!("Failed to save scanner config to NVS: {:?}", e);
+                                }
+                            }
+                        }
+
+                        if let Ok(mut callback) = event_cb.lock() {
+                            callback(ServerEvent::BarcodeEncoding(encoding));
+                        }
+                    }
+
+                    // Redirect back to root
+                    let mut resp = req.into_response(302, Some("Found"), &[("Location", "/")])?;
+                    resp.write_all(b"Redirecting...\n")?;
+                    Ok(())
+                },
+            )?;
+        }
+
+        // /barcode-symbology route: enable/disable a symbology, persist to NVS, emit event
+        {
+            let scanner_cfg = scanner_cfg.clone();
+            let nvs_handle = nvs_handle.clone();
+            let event_cb = event_callback.clone();
+
+            server.fn_handler::<anyhow::Error, _>(
+                "/barcode-symbology",
+                Method::Get,
+                move |req| {
+                    let uri = req.uri();
+                    let symbology =
+                        query_param(uri, "sym").and_then(|v| v.parse::<Symbology>().ok());
+                    let enabled = query_param(uri, "action").map(|v| v == "on");
+
+                    if let (Some(symbology), Some(enabled)) = (symbology, enabled) {
+                        log::info!(
+                            "Received barcode symbology request: {} = {}",
+                            symbology,
+                            enabled
+                        );
+
+                        {
+                            let mut cfg = scanner_cfg.lock().unwrap();
+                            cfg.symbologies.insert(symbology, enabled);
+
+                            if let Ok(nvs) = nvs_handle.lock() {
+                                if let Err(e) = cfg.save(&nvs) {
+                                    log::warn!("Failed to save scanner config to NVS: {:?}", e);
+                                }
+                            }
+                        }
+
+                        if let Ok(mut callback) = event_cb.lock() {
+                            callback(ServerEvent::BarcodeSymbology { symbology, enabled });
+                        }
+                    }
+
+                    // Redirect back to root
+                    let mut resp = req.into_response(302, Some("Found"), &[("Location", "/")])?;
+                    resp.write_all(b"Redirecting...\n")?;
+                    Ok(())
+                },
+            )?;
+        }
+
+        // /barcode-dedupe route: set the repeat-scan window, persist to NVS, emit event
+        {
+            let scanner_cfg = scanner_cfg.clone();
+            let nvs_handle = nvs_handle.clone();
+            let event_cb = event_callback.clone();
+
+            server.fn_handler::<anyhow::Error, _>("/barcode-dedupe", Method::Get, move |req| {
+                let window_ms = query_param(req.uri(), "window_ms")
+                    .and_then(|v| v.parse::<u32>().ok())
+                    .map(|ms| ms.min(60_000));
+
+                if let Some(window_ms) = window_ms {
+                    log::info!("Received barcode dedupe window request: {} ms", window_ms);
+
+                    {
+                        let mut cfg = scanner_cfg.lock().unwrap();
+                        cfg.dedupe_window_ms = window_ms;
+
+                        if let Ok(nvs) = nvs_handle.lock() {
+                            if let Err(e) = cfg.save(&nvs) {
+                                log::warn!("Failed to save scanner config to NVS: {:?}", e);
+                            }
+                        }
+                    }
+
+                    if let Ok(mut callback) = event_cb.lock() {
+                        callback(ServerEvent::BarcodeDedupeWindow(Duration::from_millis(
+                            window_ms as u64,
+                        )));
+                    }
+                }
+
+                // Redirect back to root
+                let mut resp = req.into_response(302, Some("Found"), &[("Location", "/")])?;
+                resp.write_all(b"Redirecting...\n")?;
+                Ok(())
+            })?;
+        }
+
+        // /barcode-aim route: switch the aiming light, persist to NVS, emit event
+        {
+            let scanner_cfg = scanner_cfg.clone();
+            let nvs_handle = nvs_handle.clone();
+            let event_cb = event_callback.clone();
+
+            server.fn_handler::<anyhow::Error, _>("/barcode-aim", Method::Get, move |req| {
+                if let Some(mode) = query_param(req.uri(), "mode").and_then(|v| v.parse().ok()) {
+                    log::info!("Received barcode aim request: {:?}", mode);
+
+                    {
+                        let mut cfg = scanner_cfg.lock().unwrap();
+                        cfg.aim = Some(mode);
+
+                        if let Ok(nvs) = nvs_handle.lock() {
+                            if let Err(e) = cfg.save(&nvs) {
+                                log::warn!("Failed to save scanner config to NVS: {:?}", e);
+                            }
+                        }
+                    }
+
+                    if let Ok(mut callback) = event_cb.lock() {
+                        callback(ServerEvent::BarcodeAim(mode));
+                    }
+                }
+
+                // Redirect back to root
+                let mut resp = req.into_response(302, Some("Found"), &[("Location", "/")])?;
+                resp.write_all(b"Redirecting...\n")?;
+                Ok(())
+            })?;
+        }
+
+        // /barcode-illumination route: switch the illumination, persist to NVS, emit event
+        {
+            let scanner_cfg = scanner_cfg.clone();
+            let nvs_handle = nvs_handle.clone();
+            let event_cb = event_callback.clone();
+
+            server.fn_handler::<anyhow::Error, _>(
+                "/barcode-illumination",
+                Method::Get,
+                move |req| {
+                    if let Some(mode) = query_param(req.uri(), "mode").and_then(|v| v.parse().ok())
+                    {
+                        log::info!("Received barcode illumination request: {:?}", mode);
+
+                        {
+                            let mut cfg = scanner_cfg.lock().unwrap();
+                            cfg.illumination = Some(mode);
+
+                            if let Ok(nvs) = nvs_handle.lock() {
+                                if let Err(e) = cfg.save(&nvs) {
+                                    log::warn!("Failed to save scanner config to NVS: {:?}", e);
+                                }
+                            }
+                        }
+
+                        if let Ok(mut callback) = event_cb.lock() {
+                            callback(ServerEvent::BarcodeIllumination(mode));
+                        }
+                    }
+
+                    // Redirect back to root
+                    let mut resp = req.into_response(302, Some("Found"), &[("Location", "/")])?;
+                    resp.write_all(b"Redirecting...\n")?;
+                    Ok(())
+                },
+            )?;
+        }
+
+        // /barcode-timing route: set the read timeout and/or interval, persist to NVS,
+        // emit events
+        {
+            let scanner_cfg = scanner_cfg.clone();
+            let nvs_handle = nvs_handle.clone();
+            let event_cb = event_callback;
+
+            server.fn_handler::<anyhow::Error, _>("/barcode-timing", Method::Get, move |req| {
+                let uri = req.uri();
+                let timeout_ms = read_timing_ms(uri, "timeout_ms");
+                let interval_ms = read_timing_ms(uri, "interval_ms");
+
+                if timeout_ms.is_some() || interval_ms.is_some() {
+                    log::info!(
+                        "Received barcode timing request: timeout {:?} ms, interval {:?} ms",
+                        timeout_ms,
+                        interval_ms
+                    );
+
+                    {
+                        let mut cfg = scanner_cfg.lock().unwrap();
+                        if timeout_ms.is_some() {
+                            cfg.read_timeout_ms = timeout_ms;
+                        }
+                        if interval_ms.is_some() {
+                            cfg.read_interval_ms = interval_ms;
+                        }
+
+                        if let Ok(nvs) = nvs_handle.lock() {
+                            if let Err(e) = cfg.save(&nvs) {
+                                log::warn!("Failed to save scanner config to NVS: {:?}", e);
+                            }
+                        }
+                    }
+
+                    if let Ok(mut callback) = event_cb.lock() {
+                        if let Some(ms) = timeout_ms {
+                            callback(ServerEvent::BarcodeReadTimeout(Duration::from_millis(
+                                ms as u64,
+                            )));
+                        }
+                        if let Some(ms) = interval_ms {
+                            callback(ServerEvent::BarcodeReadInterval(Duration::from_millis(
+                                ms as u64,
+                            )));
+                        }
+                    }
+                }
+
+                // Redirect back to root
+                let mut resp = req.into_response(302, Some("Found"), &[("Location", "/")])?;
+                resp.write_all(b"Redirecting...\n")?;
+                Ok(())
+            })?;
+        }
+
+        // /barcode-stats route: scan and error counters as JSON
+        {
+            let barcode = barcode.clone();
+
+            server.fn_handler::<anyhow::Error, _>("/barcode-stats", Method::Get, move |req| {
+                let body = scan_stats_json(&barcode.stats());
+
+                let mut resp =
+                    req.into_response(200, None, &[("Content-Type", "application/json")])?;
+                resp.write_all(body.as_bytes())?;
+                Ok(())
+            })?;
+        }
+
+        // /barcode-stats-reset route: zero the scan counters
+        {
+            let barcode = barcode.clone();
+
+            server.fn_handler::<anyhow::Error, _>(
+                "/barcode-stats-reset",
+                Method::Get,
+                move |req| {
+                    log::info!("Resetting scan statistics");
+                    barcode.reset_stats();
+
+                    // Redirect back to root
+                    let mut resp = req.into_response(302, Some("Found"), &[("Location", "/")])?;
+                    resp.write_all(b"Redirecting...\n")?;
+                    Ok(())
+                },
+            )?;
+        }
+
+        // /scanner-profile GET route: download the scanner's settings as JSON
+        {
+            let barcode = barcode.clone();
+
+            server.fn_handler::<anyhow::Error, _>("/scanner-profile", Method::Get, move |req| {
+                match barcode.read_settings() {
+                    Ok(settings) => {
+                        let headers = [
+                            ("Content-Type", "application/json"),
+                            (
+                                "Content-Disposition",
+                                r#"attachment; filename="scanner-profile.json""#,
+                            ),
+                        ];
+                        let mut resp = req.into_response(200, None, &headers)?;
+                        resp.write_all(settings.to_json().as_bytes())?;
+                    }
+                    Err(e) => {
+                        log::warn!("Failed to read scanner settings: {:?}", e);
+                        let mut resp =
+                            req.into_response(503, None, &[("Content-Type", "text/plain")])?;
+                        resp.write_all(format!("{:#}\n", e).as_bytes())?;
+                    }
+                }
+                Ok(())
+            })?;
+        }
+
+        // /scanner-profile POST route: apply an uploaded profile, persist the
+        // settings the firmware re-applies at boot, optionally save to flash
+        {
+            let barcode = barcode.clone();
+            let scanner_cfg = scanner_cfg.clone();
+            let nvs_handle = nvs_handle.clone();
+
+            server.fn_handler::<anyhow::Error, _>(
+                "/scanner-profile",
+                Method::Post,
+                move |mut req| {
+                    let len = req.content_len().unwrap_or(0) as usize;
+                    if len > MAX_PROFILE_LEN {
+                        let mut resp =
+                            req.into_response(413, None, &[("Content-Type", "text/plain")])?;
+                        resp.write_all(b"Profile too large\n")?;
+                        return Ok(());
+                    }
+                    let mut body = vec![0; len];
+                    req.read_exact(&mut body)?;
+                    let body = String::from_utf8_lossy(&body).into_owned();
+
+                    // The root page form posts `profile=...&save=1`; API clients
+                    // send the JSON object itself and `?save=1`.
+                    let form = !body.trim_start().starts_with('{');
+                    let (profile, save) = if form {
+                        let profile = form_param(&body, "profile").map(url_decode);
+                        (
+                            profile.unwrap_or_default(),
+                            form_param(&body, "save") == Some("1"),
+                        )
+                    } else {
+                        (body, query_param(req.uri(), "save") == Some("1"))
+                    };
+
+                    let result = barcode.read_settings().and_then(|mut settings| {
+                        settings.update_from_json(&profile)?;
+                        log::info!("Applying scanner profile (save to flash: {})", save);
+                        barcode.apply_settings(&settings)?;
+                        if save {
+                            barcode.save_to_flash()?;
+                        }
+                        Ok(settings)
+                    });
+
+                    let settings = match result {
+                        Ok(settings) => settings,
+                        Err(e) => {
+                            log::warn!("Rejected scanner profile: {:?}", e);
+                            let mut resp =
+                                req.into_response(400, None, &[("Content-Type", "text/plain")])?;
+                            resp.write_all(format!("{:#}\n", e).as_bytes())?;
+                            return Ok(());
+                        }
+                    };
+
+                    // Keep the boot-time settings from undoing the profile.
+                    {
+                        let mut cfg = scanner_cfg.lock().unwrap();
+                        cfg.scan_mode = Some(settings.scan_mode);
+                        cfg.encoding = Some(settings.encoding);
+                        cfg.symbologies = Symbology::ALL
+                            .into_iter()
+                            .map(|sym| (sym, settings.enabled.contains(&sym)))
+                            .collect();
+                        cfg.aim = Some(settings.aim);
+                        cfg.illumination = Some(settings.illumination);
+                        cfg.read_timeout_ms = Some(settings.read_timeout.as_millis() as u32);
+                        cfg.read_interval_ms = Some(settings.read_interval.as_millis() as u32);
+
+                        if let Ok(nvs) = nvs_handle.lock() {
+                            if let Err(e) = cfg.save(&nvs) {
+                                log::warn!("Failed to save scanner config to NVS: {:?}", e);
+                            }
+                        }
+                    }
+
+                    // Redirect back to root
+                    let mut resp = req.into_response(302, Some("Found"), &[("Location", "/")])?;
+                    resp.write_all(b"Redirecting...\n")?;
+                    Ok(())
+                },
+            )?;
+        }
+
+        // /scanner-save route: save the scanner's current settings to its flash
+        {
+            let barcode = barcode.clone();
+
+            server.fn_handler::<anyhow::Error, _>("/scanner-save", Method::Get, move |req| {
+                log::info!("Saving scanner settings to flash");
+                if let Err(e) = barcode.save_to_flash() {
+                    log::warn!("Failed to save scanner settings to flash: {:?}", e);
+                    let mut resp =
+                        req.into_response(503, None, &[("Content-Type", "text/plain")])?;
+                    resp.write_all(format!("{:#}\n", e).as_bytes())?;
+                    return Ok(());
+                }
+
+                // Redirect back to root
+                let mut resp = req.into_response(302, Some("Found"), &[("Location", "/")])?;
+                resp.write_all(b"Redirecting...\n")?;
+                Ok(())
+            })?;
+        }
+
+        // /barcode-scan route: start a scan and long-poll for the result as JSON
+        {
+            server.fn_handler::<anyhow::Error, _>("/barcode-scan", Method::Get, move |req| {
+                let timeout_ms = query_param(req.uri(), "timeout_ms")
+                    .and_then(|v| v.parse::<u64>().ok())
+                    .unwrap_or(MAX_SCAN_WAIT_MS)
+                    .clamp(100, MAX_SCAN_WAIT_MS);
+
+                log::info!("Received one-shot scan request ({} ms)", timeout_ms);
+
+                let (status, body) = match barcode.scan_once(Duration::from_millis(timeout_ms)) {
+                    Ok(scan) => {
+                        let symbology = scan
+                            .symbology
+                            .map(|sym| json_string(sym.as_str()))
+                            .unwrap_or_else(|| "null".into());
+                        let body = format!(
+                            r#"{{"symbology":{},"payload":{}}}"#,
+                            symbology,
+                            json_string(&scan.payload)
+                        );
+                        (200, body)
+                    }
+                    Err(e) => {
+                        let status = match e {
+                            ScanError::Timeout => 504,
+                            ScanError::Busy => 409,
+                            ScanError::Disconnected => 503,
+                            ScanError::Invalid(_) | ScanError::Binary(_) => 422,
+                            ScanError::Trigger(_) | ScanError::WorkerStopped => 500,
+                        };
+                        let body = format!(r#"{{"error":{}}}"#, json_string(&e.to_string()));
+                        (status, body)
+                    }
+                };
+
+                let mut resp =
+                    req.into_response(status, None, &[("Content-Type", "application/json")])?;
+                resp.write_all(body.as_bytes())?;
+                Ok(())
+            })?;
+        }
+
+        // /history route: one page of the scan history as JSON, newest first
+        {
+            let history = history.clone();
+
+            server.fn_handler::<anyhow::Error, _>("/history", Method::Get, move |req| {
+                let uri = req.uri();
+                let offset = query_param(uri, "offset")
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .unwrap_or(0);
+                let limit = query_param(uri, "limit")
+                    .and_then(|v| v.parse::<usize>().ok())
+                    .unwrap_or(20)
+                    .clamp(1, scan_history::CAPACITY);
+
+                let body = {
+                    let history = history.lock().unwrap();
+                    let entries = history
+                        .page(offset, limit)
+                        .map(history_entry_json)
+                        .collect::<Vec<_>>()
+                        .join(",");
+                    format!(
+                        r#"{{"total":{},"offset":{},"limit":{},"entries":[{}]}}"#,
+                        history.count(),
+                        offset,
+                        limit,
+                        entries
+                    )
+                };
+
+                let mut resp =
+                    req.into_response(200, None, &[("Content-Type", "application/json")])?;
+                resp.write_all(body.as_bytes())?;
+                Ok(())
+            })?;
+        }
+
+        // /history/export route: the whole scan history as CSV or JSON
+        {
+            server.fn_handler::<anyhow::Error, _>("/history/export", Method::Get, move |req| {
+                let csv = query_param(req.uri(), "format") == Some("csv");
+
+                let body = {
+                    let history = history.lock().unwrap();
+                    let entries = history.page(0, history.count());
+                    if csv {
+                        let mut body = String::from("seq,timestamp,symbology,outcome,payload\r\n");
+                        for entry in entries {
+                            body.push_str(&history_entry_csv(entry));
+                        }
+                        body
+                    } else {
+                        let entries = entries.map(history_entry_json).collect::<Vec<_>>();
+                        format!("[{}]", entries.join(","))
+                    }
+                };
+
+                let headers = if csv {
+                    [
+                        ("Content-Type", "text/csv"),
+                        (
+                            "Content-Disposition",
+                            r#"attachment; filename="scan-history.csv""#,
+                        ),
+                    ]
+                } else {
+                    [
+                        ("Content-Type", "application/json"),
+                        (
+                            "Content-Disposition",
+                            r#"attachment; filename="scan-history.json""#,
+                        ),
+                    ]
+                };
+                let mut resp = req.into_response(200, None, &headers)?;
+                resp.write_all(body.as_bytes())?;
+                Ok(())
+            })?;
+        }
+
+        // /rules route: the rule table as text, one rule per line
+        {
+            let rules = rules.clone();
+
+            server.fn_handler::<anyhow::Error, _>("/rules", Method::Get, move |req| {
+                let body = rules.lock().unwrap().to_string();
+                let mut resp = req.into_response(200, None, &[("Content-Type", "text/plain")])?;
+                resp.write_all(body.as_bytes())?;
+                Ok(())
+            })?;
+        }
+
+        // /rules-add route: append a rule, persist to NVS
+        {
+            let rules = rules.clone();
+            let nvs_handle = nvs_handle.clone();
+
+            server.fn_handler::<anyhow::Error, _>("/rules-add", Method::Get, move |req| {
+                let rule = query_param(req.uri(), "rule").map(url_decode);
+                let result = rule
+                    .as_deref()
+                    .unwrap_or("")
+                    .parse::<Rule>()
+                    .and_then(|rule| {
+                        log::info!("Received add rule request: {}", rule);
+
+                        let mut rules = rules.lock().unwrap();
+                        rules.add(rule)?;
+                        if let Ok(nvs) = nvs_handle.lock() {
+                            if let Err(e) = crate::rules::save(&rules, &nvs) {
+                                log::warn!("Failed to save rules to NVS: {:?}", e);
+                            }
+                        }
+                        Ok(())
+                    });
+
+                if let Err(e) = result {
+                    log::warn!("Rejected rule: {:?}", e);
+                    let mut resp =
+                        req.into_response(400, None, &[("Content-Type", "text/plain")])?;
+                    resp.write_all(format!("{:#}\n", e).as_bytes())?;
+                    return Ok(());
+                }
+
+                // Redirect back to root
+                let mut resp = req.into_response(302, Some("Found"), &[("Location", "/")])?;
+                resp.write_all(b"Redirecting...\n")?;
+                Ok(())
+            })?;
+        }
+
+        // /rules-delete route: remove a rule by index, persist to NVS
+        {
+            let nvs_handle = nvs_handle.clone();
+
+            server.fn_handler::<anyhow::Error, _>("/rules-delete", Method::Get, move |req| {
+                let index = query_param(req.uri(), "index").and_then(|v| v.parse::<usize>().ok());
+
+                if let Some(index) = index {
+                    let mut rules = rules.lock().unwrap();
+                    if let Some(rule) = rules.remove(index) {
+                        log::info!("Removed rule {}: {}", index, rule);
+
+                        if let Ok(nvs) = nvs_handle.lock() {
+                            if let Err(e) = crate::rules::save(&rules, &nvs) {
+                                log::warn!("Failed to save rules to NVS: {:?}", e);
+                            }
+                        }
+                    }
+                }
+
+                // Redirect back to root
+                let mut resp = req.into_response(302, Some("Found"), &[("Location", "/")])?;
+                resp.write_all(b"Redirecting...\n")?;
+                Ok(())
+            })?;
+        }
+
         Ok(Self { _server: server })
     }
 }
+
+/// A read timeout or interval in ms from the query string: 0, or 100 to
+/// [`MAX_READ_TIMING_MS`] rounded down to the scanner's 100 ms steps.
+fn read_timing_ms(uri: &str, key: &str) -> Option<u32> {
+    query_param(uri, key)
+        .and_then(|v| v.parse::<u32>().ok())
+        .filter(|ms| *ms == 0 || (100..=MAX_READ_TIMING_MS).contains(ms))
+        .map(|ms| ms / 100 * 100)
+}
+
+/// Look up `key` in the query string of `uri`.
+fn query_param<'a>(uri: &'a str, key: &str) -> Option<&'a str> {
+    form_param(&uri[uri.find('?')? + 1..], key)
+}
+
+/// Look up `key` in a `application/x-www-form-urlencoded` string.
+fn form_param<'a>(form: &'a str, key: &str) -> Option<&'a str> {
+    form.split('&').find_map(|pair| {
+        let mut it = pair.splitn(2, '=');
+        (it.next() == Some(key)).then(|| it.next().unwrap_or(""))
+    })
+}
+
+/// Decode a `application/x-www-form-urlencoded` query value.
+fn url_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+                match hex.and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                    Some(byte) => {
+                        out.push(byte);
+                        i += 2;
+                    }
+                    None => out.push(b'%'),
+                }
+            }
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Escape `s` for use in HTML text and attribute values.
+fn html_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// One history entry as a JSON object.
+fn history_entry_json(entry: &HistoryEntry) -> String {
+    let symbology = entry
+        .symbology
+        .map(|sym| json_string(sym.as_str()))
+        .unwrap_or_else(|| "null".into());
+    format!(
+        r#"{{"seq":{},"timestamp":{},"symbology":{},"outcome":"{}","payload":{}}}"#,
+        entry.seq,
+        entry.timestamp,
+        symbology,
+        entry.outcome.as_str(),
+        json_string(&entry.payload)
+    )
+}
+
+/// Scan counters as a JSON object. Reads without Code ID output are
+/// counted under `"unknown"`; times are in seconds.
+fn scan_stats_json(stats: &ScanStats) -> String {
+    let by_symbology = stats
+        .by_symbology
+        .iter()
+        .map(|(sym, count)| {
+            let name = sym.map_or("unknown", |sym| sym.as_str());
+            format!("{}:{}", json_string(name), count)
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    let since_last_scan = stats
+        .since_last_scan()
+        .map_or_else(|| "null".into(), |age| age.as_secs().to_string());
+    format!(
+        r#"{{"total_scans":{},"by_symbology":{{{}}},"invalid":{},"overflows":{},"read_errors":{},"dedupe_drops":{},"since_last_scan_s":{},"uptime_s":{}}}"#,
+        stats.total_scans,
+        by_symbology,
+        stats.invalid,
+        stats.overflows,
+        stats.read_errors,
+        stats.dedupe_drops,
+        since_last_scan,
+        stats.uptime().as_secs()
+    )
+}
+
+/// One history entry as a CSV row, quoting the payload.
+fn history_entry_csv(entry: &HistoryEntry) -> String {
+    format!(
+        "{},{},{},{},\"{}\"\r\n",
+        entry.seq,
+        entry.timestamp,
+        entry.symbology.map_or("", |sym| sym.as_str()),
+        entry.outcome.as_str(),
+        entry.payload.replace('"', "\"\"")
+    )
+}
@@ -1,10 +1,10 @@
-use core::convert::TryInto;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc, Mutex};
+use std::time::Instant;
 use std::{thread, time::Duration};
 
-use embedded_svc::wifi::{AuthMethod, ClientConfiguration, Configuration};
-
 use esp_idf_hal::{
-    gpio::PinDriver,
+    gpio::{PinDriver, Pull},
     prelude::*,
     spi::{config::Config as SpiConfig, SpiDeviceDriver, SpiDriver, SpiDriverConfig},
     uart::{config::Config as UartConfig, UartDriver},
@@ -12,22 +12,55 @@ use esp_idf_hal::{
 use esp_idf_svc::eventloop::EspSystemEventLoop;
 use esp_idf_svc::log::EspLogger;
 use esp_idf_svc::nvs::{EspDefaultNvs, EspDefaultNvsPartition};
+use esp_idf_svc::sntp::EspSntp;
 use esp_idf_svc::wifi::{BlockingWifi, EspWifi};
 
 use log::info;
 
-const SSID: &str = include_str!("../.wifi_ssid");
-const PASSWORD: &str = include_str!("../.wifi_password");
+/// How long to wait for a Wi-Fi QR code before trying the stored network
+/// again.
+const WIFI_PROVISIONING_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Time for each scan while waiting for a Wi-Fi QR code.
+const WIFI_PROVISIONING_SCAN: Duration = Duration::from_secs(10);
+
+/// Whether the GM65 TRIG line is wired to GPIO25. Without it, scans are
+/// triggered over UART with the serial trigger command.
+const BARCODE_TRIG_WIRED: bool = true;
+
+/// Silence on the scanner UART that ends a scan.
+const BARCODE_IDLE_GAP: Duration = Duration::from_millis(50);
+
+/// Largest scan to accept; a full version 40 QR code holds 2953 bytes.
+const BARCODE_MAX_SCAN_LEN: usize = 3072;
+
+/// Whether our Code 39 labels carry a mod 43 check character.
+const BARCODE_CODE39_CHECK_DIGIT: bool = false;
+
+/// Baud rate to move the scanner to once it is found; faster rates cut the
+/// transfer time of large 2D codes. `None` keeps the rate it answered on.
+const BARCODE_BAUD_RATE: Option<BaudRate> = Some(BaudRate::B115200);
 
-mod barcode;
 mod blinker;
 mod epaper;
 mod http_server;
+mod json;
+mod rules;
+mod scan_history;
+mod uart;
+mod wifi_provisioning;
 
-use barcode::{BarcodeEvent, BarcodeScanner};
 use blinker::Blinker;
-use epaper::{DisplayJob, DisplayManager};
-use http_server::{BlinkConfig, HttpServer, ServerEvent};
+use epaper::{DisplayHandle, DisplayJob, DisplayManager};
+use http_server::{BlinkConfig, HttpServer, ScannerConfig, ServerEvent};
+use rules::RuleRunner;
+use scan_history::{HistoryRecorder, ScanHistory, ScanOutcome};
+use scanner::barcode::{
+    self, BarcodeEvent, BarcodeHandle, BarcodeScanner, BaudRate, BinaryScan, Framing, ScanContent,
+    ScanError, ScanResult, Symbology,
+};
+use uart::UartPort;
+use wifi_provisioning::WifiCredentials;
 
 fn main() -> anyhow::Result<()> {
     esp_idf_svc::sys::link_patches();
@@ -48,6 +81,8 @@ fn main() -> anyhow::Result<()> {
     let nvs_partition = EspDefaultNvsPartition::take()?;
     let nvs_for_wifi = nvs_partition.clone();
     let nvs_partition_for_server = nvs_partition.clone();
+    let nvs_partition_for_history = nvs_partition.clone();
+    let nvs_partition_for_wifi_credentials = nvs_partition.clone();
     let nvs = EspDefaultNvs::new(nvs_partition, "blink", true)?;
 
     // Load initial blink configuration
@@ -105,8 +140,10 @@ fn main() -> anyhow::Result<()> {
     // --- Barcode Scanner (GM65) Setup ---
     info!("Setting up barcode scanner...");
 
-    // UART configuration for GM65: 9600 8N1
-    let uart_config = UartConfig::new().baudrate(Hertz(9600));
+    // UART configuration for GM65: 9600 8N1 (factory default). The barcode
+    // worker probes the other rates if the scanner doesn't answer here. Its
+    // reader thread waits on the driver's event queue.
+    let uart_config = UartConfig::new().baudrate(Hertz(9600)).queue_size(16);
 
     let uart = UartDriver::new(
         peripherals.uart1,
@@ -119,30 +156,95 @@ fn main() -> anyhow::Result<()> {
     info!("UART driver created for barcode scanner");
 
     // Set up control pins for GM65
-    let trigger = PinDriver::output(pins.gpio25)?; // TRIG
+    let trigger = if BARCODE_TRIG_WIRED {
+        Some(PinDriver::output(pins.gpio25)?) // TRIG
+    } else {
+        None
+    };
     let led = PinDriver::output(pins.gpio26)?; // LED
     let beep = PinDriver::output(pins.gpio27)?; // BEEP
     info!("Barcode scanner control pins configured");
 
+    // Scan history, kept in its own NVS namespace
+    let history = ScanHistory::load(EspDefaultNvs::new(
+        nvs_partition_for_history,
+        "history",
+        true,
+    )?);
+    info!("Loaded {} scan history entries", history.count());
+    let history = Arc::new(Mutex::new(history));
+    let history_recorder = HistoryRecorder::start(history.clone())?;
+
+    // Scan rules; scans are matched in the rules worker, started once the
+    // scanner handle exists.
+    let rules = rules::load(&nvs);
+    info!("Loaded {} scan rules", rules.rules().len());
+    let rules = Arc::new(Mutex::new(rules));
+    let (rule_scan_tx, rule_scan_rx) = mpsc::channel();
+
+    // While waiting for a Wi-Fi QR code, scans go to the provisioning loop
+    // only, so credentials stay out of the history and rules.
+    let provisioning = Arc::new(AtomicBool::new(false));
+    let provisioning_for_barcode = provisioning.clone();
+
     // Start barcode scanner worker
     let display_handle_for_barcode = display_handle.clone();
     let barcode_scanner = BarcodeScanner::start(
-        uart,
-        Some(trigger),
+        UartPort::new(uart),
+        trigger,
         Some(led),
         Some(beep),
         move |event| match event {
-            BarcodeEvent::Scanned(code) => {
-                info!("Scanned barcode: {}", code);
-
-                // Display the scanned code on line 0 of the e-paper
-                if let Err(e) = display_handle_for_barcode.submit(DisplayJob::UpdateLine {
-                    line_number: 0,
-                    text: code,
+            event if event.is_scan() && provisioning_for_barcode.load(Ordering::Relaxed) => {
+                info!("Scanned barcode during Wi-Fi setup");
+            }
+            BarcodeEvent::Scanned(scan) => {
+                // Wi-Fi passwords would otherwise end up on the HTTP pages.
+                let payload = barcode::redact_wifi_password(&scan.payload);
+                info!("Scanned barcode ({:?}): {}", scan.symbology, payload);
+                history_recorder.record(scan.symbology, ScanOutcome::Ok, &payload, scan.timestamp);
+
+                if let Err(e) = rule_scan_tx.send(scan) {
+                    log::error!("Failed to pass scan to rules worker: {:?}", e);
+                }
+            }
+            BarcodeEvent::Binary(scan) => {
+                info!(
+                    "Scanned binary barcode ({:?}): {} bytes",
+                    scan.symbology,
+                    scan.data.len()
+                );
+                let hex: String = scan.data.iter().map(|b| format!("{:02X}", b)).collect();
+                history_recorder.record(scan.symbology, ScanOutcome::Binary, &hex, scan.timestamp);
+
+                if let Err(e) = display_handle_for_barcode.submit(DisplayJob::UpdateLines {
+                    first_line: 0,
+                    lines: binary_display_lines(&scan),
                 }) {
                     log::error!("Failed to submit barcode display job: {:?}", e);
                 }
             }
+            BarcodeEvent::Invalid { scan, reason } => {
+                let payload = barcode::redact_wifi_password(&scan.payload);
+                log::warn!(
+                    "Rejected barcode ({:?}) {}: {}",
+                    scan.symbology,
+                    payload,
+                    reason
+                );
+                history_recorder.record(
+                    scan.symbology,
+                    ScanOutcome::Invalid,
+                    &payload,
+                    scan.timestamp,
+                );
+            }
+            BarcodeEvent::Connected => {
+                info!("Barcode scanner connected");
+            }
+            BarcodeEvent::Disconnected => {
+                log::warn!("Barcode scanner disconnected");
+            }
             BarcodeEvent::Error(err) => {
                 log::warn!("Barcode scanner error: {}", err);
             }
@@ -150,7 +252,101 @@ fn main() -> anyhow::Result<()> {
     )?;
 
     let barcode_handle = barcode_scanner.handle();
-    info!("Barcode scanner started");
+    info!(
+        "Barcode scanner started (trigger mode: {:?})",
+        barcode_handle.trigger_mode()
+    );
+
+    let display_handle_for_rules = display_handle.clone();
+    RuleRunner::start(
+        rule_scan_rx,
+        rules.clone(),
+        display_handle.clone(),
+        barcode_handle.clone(),
+        move |scan| {
+            if let Err(e) = display_handle_for_rules.submit(DisplayJob::UpdateLines {
+                first_line: 0,
+                lines: scan_display_lines(scan),
+            }) {
+                log::error!("Failed to submit barcode display job: {:?}", e);
+            }
+        },
+    )?;
+
+    // The GM65 factory default sends no tail after a scan, so end scans on a
+    // pause in the data rather than waiting for CR/LF.
+    barcode_handle.set_framing(Framing::IdleGap(BARCODE_IDLE_GAP))?;
+    barcode_handle.set_max_scan_len(BARCODE_MAX_SCAN_LEN)?;
+    barcode_handle.set_code39_check_digit(BARCODE_CODE39_CHECK_DIGIT)?;
+    barcode_handle.set_scan_feedback(true)?;
+
+    if let Some(baud) = BARCODE_BAUD_RATE {
+        if let Err(e) = barcode_handle.set_baud_rate(baud) {
+            log::warn!("Failed to switch barcode scanner to {}: {:?}", baud, e);
+        }
+    }
+    match barcode_handle.baud_rate() {
+        Ok(baud) => info!("Barcode scanner baud rate: {}", baud),
+        Err(e) => log::warn!("Failed to query barcode scanner baud rate: {:?}", e),
+    }
+
+    match barcode_handle.info() {
+        Ok(scanner_info) => info!("Barcode scanner info: {:?}", scanner_info),
+        Err(e) => log::warn!("Failed to query barcode scanner info: {:?}", e),
+    }
+
+    // Re-apply persisted scanner settings
+    let scanner_cfg = ScannerConfig::load(&nvs);
+    info!("Initial scanner config from NVS: {:?}", scanner_cfg);
+    if let Some(mode) = scanner_cfg.scan_mode {
+        if let Err(e) = barcode_handle.set_scan_mode(mode) {
+            log::warn!("Failed to apply barcode scan mode: {:?}", e);
+        }
+    }
+    match barcode_handle.scan_mode() {
+        Ok(mode) => info!("Barcode scan mode: {:?}", mode),
+        Err(e) => log::warn!("Failed to read barcode scan mode: {:?}", e),
+    }
+    if let Some(encoding) = scanner_cfg.encoding {
+        if let Err(e) = barcode_handle.set_encoding(encoding) {
+            log::warn!("Failed to apply barcode payload encoding: {:?}", e);
+        }
+    }
+    let dedupe_window = Duration::from_millis(scanner_cfg.dedupe_window_ms as u64);
+    if let Err(e) = barcode_handle.set_dedupe_window(dedupe_window) {
+        log::warn!("Failed to apply barcode dedupe window: {:?}", e);
+    }
+    for (&symbology, &enabled) in &scanner_cfg.symbologies {
+        if let Err(e) = barcode_handle.set_symbology_enabled(symbology, enabled) {
+            log::warn!("Failed to apply barcode symbology {}: {:?}", symbology, e);
+        }
+    }
+    match barcode_handle.enabled_symbologies() {
+        Ok(symbologies) => info!("Barcode symbologies enabled: {:?}", symbologies),
+        Err(e) => log::warn!("Failed to read barcode symbologies: {:?}", e),
+    }
+    if let Some(mode) = scanner_cfg.aim {
+        if let Err(e) = barcode_handle.set_aim(mode) {
+            log::warn!("Failed to apply barcode aim mode: {:?}", e);
+        }
+    }
+    if let Some(mode) = scanner_cfg.illumination {
+        if let Err(e) = barcode_handle.set_illumination(mode) {
+            log::warn!("Failed to apply barcode illumination: {:?}", e);
+        }
+    }
+    if let Some(timeout_ms) = scanner_cfg.read_timeout_ms {
+        let timeout = Duration::from_millis(timeout_ms as u64);
+        if let Err(e) = barcode_handle.set_read_timeout(timeout) {
+            log::warn!("Failed to apply barcode read timeout: {:?}", e);
+        }
+    }
+    if let Some(interval_ms) = scanner_cfg.read_interval_ms {
+        let interval = Duration::from_millis(interval_ms as u64);
+        if let Err(e) = barcode_handle.set_read_interval(interval) {
+            log::warn!("Failed to apply barcode read interval: {:?}", e);
+        }
+    }
 
     // --- Wi-Fi setup ---
     let sys_loop = EspSystemEventLoop::take()?;
@@ -160,16 +356,49 @@ fn main() -> anyhow::Result<()> {
         sys_loop,
     )?;
 
-    connect_wifi(&mut wifi)?;
+    // Join the stored network, or wait for a Wi-Fi QR code until one works.
+    let wifi_nvs = EspDefaultNvs::new(nvs_partition_for_wifi_credentials, "wifi", true)?;
+    let mut connected = match WifiCredentials::load(&wifi_nvs) {
+        Some(credentials) => {
+            info!("Stored Wi-Fi network: {:?}", credentials);
+            match connect_wifi(&mut wifi, &credentials) {
+                Ok(()) => true,
+                Err(e) => {
+                    log::warn!("Failed to join {}: {:?}", credentials.ssid, e);
+                    false
+                }
+            }
+        }
+        None => {
+            info!("No Wi-Fi network stored");
+            false
+        }
+    };
+    while !connected {
+        connected = provision_wifi(
+            &mut wifi,
+            &wifi_nvs,
+            &barcode_handle,
+            &display_handle,
+            &provisioning,
+        ) || reconnect_stored_wifi(&mut wifi, &wifi_nvs);
+    }
 
     let ip_info = wifi.wifi().sta_netif().get_ip_info()?;
     info!("WiFi up, DHCP info: {:?}", ip_info);
     info!("Open http://{} in your browser", ip_info.ip);
 
+    // Set the clock so scan history timestamps are real dates.
+    let sntp = EspSntp::new_default()?;
+    info!("SNTP started");
+
     // --- HTTP server with event-driven config updates ---
     let nvs_for_server = EspDefaultNvs::new(nvs_partition_for_server, "blink", true)?;
+    let barcode_handle_for_server = barcode_handle.clone();
+    let barcode_handle_for_wifi = barcode_handle.clone();
+    let display_handle_for_wifi = display_handle.clone();
 
-    let _server = HttpServer::start(initial_cfg, nvs_for_server, move |event| match event {
+    let on_server_event = move |event: ServerEvent| match event {
         ServerEvent::ConfigUpdated(config) => {
             info!("Received config update event: {:?}", config);
             if let Err(e) = blink_handle.update_config(config.enabled, config.period_ms) {
@@ -206,33 +435,327 @@ fn main() -> anyhow::Result<()> {
                 log::error!("Failed to set barcode beep: {:?}", e);
             }
         }
-    })?;
+        ServerEvent::BarcodeScanMode(mode) => {
+            info!("Received barcode scan mode event: {:?}", mode);
+            if let Err(e) = barcode_handle.set_scan_mode(mode) {
+                log::error!("Failed to set barcode scan mode: {:?}", e);
+            }
+        }
+        ServerEvent::BarcodeEncoding(encoding) => {
+            info!("Received barcode encoding event: {:?}", encoding);
+            if let Err(e) = barcode_handle.set_encoding(encoding) {
+                log::error!("Failed to set barcode payload encoding: {:?}", e);
+            }
+        }
+        ServerEvent::BarcodeSymbology { symbology, enabled } => {
+            info!(
+                "Received barcode symbology event: {} = {}",
+                symbology, enabled
+            );
+            if let Err(e) = barcode_handle.set_symbology_enabled(symbology, enabled) {
+                log::error!("Failed to set barcode symbology: {:?}", e);
+            }
+        }
+        ServerEvent::BarcodeFeedback(feedback) => {
+            info!("Received barcode feedback event: {:?}", feedback);
+            if let Err(e) = barcode_handle.play_feedback(feedback) {
+                log::error!("Failed to play barcode feedback: {:?}", e);
+            }
+        }
+        ServerEvent::BarcodeDedupeWindow(window) => {
+            info!("Received barcode dedupe window event: {:?}", window);
+            if let Err(e) = barcode_handle.set_dedupe_window(window) {
+                log::error!("Failed to set barcode dedupe window: {:?}", e);
+            }
+        }
+        ServerEvent::BarcodeAim(mode) => {
+            info!("Received barcode aim event: {:?}", mode);
+            if let Err(e) = barcode_handle.set_aim(mode) {
+                log::error!("Failed to set barcode aim mode: {:?}", e);
+            }
+        }
+        ServerEvent::BarcodeIllumination(mode) => {
+            info!("Received barcode illumination event: {:?}", mode);
+            if let Err(e) = barcode_handle.set_illumination(mode) {
+                log::error!("Failed to set barcode illumination: {:?}", e);
+            }
+        }
+        ServerEvent::BarcodeReadTimeout(timeout) => {
+            info!("Received barcode read timeout event: {:?}", timeout);
+            if let Err(e) = barcode_handle.set_read_timeout(timeout) {
+                log::error!("Failed to set barcode read timeout: {:?}", e);
+            }
+        }
+        ServerEvent::BarcodeReadInterval(interval) => {
+            info!("Received barcode read interval event: {:?}", interval);
+            if let Err(e) = barcode_handle.set_read_interval(interval) {
+                log::error!("Failed to set barcode read interval: {:?}", e);
+            }
+        }
+    };
+
+    let _server = HttpServer::start(
+        initial_cfg,
+        scanner_cfg,
+        barcode_handle_for_server,
+        history,
+        rules,
+        nvs_for_server,
+        on_server_event,
+    )?;
 
     info!("HTTP server started");
 
     // Keep objects alive
-    core::mem::forget(wifi);
+    core::mem::forget(sntp);
     core::mem::forget(display_manager);
 
+    // Holding the BOOT button re-runs provisioning, e.g. to move networks.
+    let (button_tx, button_rx) = mpsc::channel();
+    let mut button = PinDriver::input(pins.gpio0)?;
+    button.set_pull(Pull::Up)?;
+    wifi_provisioning::watch_button(button, move || {
+        button_tx.send(()).ok();
+    })?;
+
+    while button_rx.recv().is_ok() {
+        info!("Provisioning button held");
+        let provisioned = provision_wifi(
+            &mut wifi,
+            &wifi_nvs,
+            &barcode_handle_for_wifi,
+            &display_handle_for_wifi,
+            &provisioning,
+        );
+        if !provisioned && !wifi.is_connected().unwrap_or(false) {
+            reconnect_stored_wifi(&mut wifi, &wifi_nvs);
+        }
+    }
+
     // Park main thread forever
     loop {
         thread::sleep(Duration::from_secs(60));
     }
 }
 
-fn connect_wifi(wifi: &mut BlockingWifi<EspWifi<'static>>) -> anyhow::Result<()> {
-    let wifi_configuration: Configuration = Configuration::Client(ClientConfiguration {
-        ssid: SSID.try_into().unwrap(),
-        bssid: None,
-        auth_method: AuthMethod::WPA2Personal,
-        password: PASSWORD.try_into().unwrap(),
-        channel: None,
-        ..Default::default()
-    });
-
-    wifi.set_configuration(&wifi_configuration)?;
-    wifi.start()?;
-    info!("Wi-Fi driver started");
+/// Label the code by kind so QR content and retail labels are easy to tell
+/// apart on the display.
+fn symbology_label(symbology: Option<Symbology>) -> &'static str {
+    match symbology {
+        Some(Symbology::Qr) => "QR",
+        Some(Symbology::Ean13 | Symbology::Ean8) => "EAN",
+        Some(Symbology::UpcA | Symbology::UpcE0 | Symbology::UpcE1) => "UPC",
+        Some(sym) => sym.as_str(),
+        None => "?",
+    }
+}
+
+/// Display lines for a binary scan: its size and the first bytes in hex.
+fn binary_display_lines(scan: &BinaryScan) -> Vec<String> {
+    let hex: Vec<String> = scan
+        .data
+        .iter()
+        .take(8)
+        .map(|b| format!("{:02X}", b))
+        .collect();
+
+    vec![
+        format!("{}: binary", symbology_label(scan.symbology)),
+        format!("{} bytes", scan.data.len()),
+        hex.join(" "),
+    ]
+}
+
+/// Lines 0-2 of the e-paper for a scan: key GS1 fields when the code carries
+/// them, a summary of recognized QR content, otherwise the code kind and raw
+/// payload.
+fn scan_display_lines(scan: &ScanResult) -> Vec<String> {
+    match scan.gs1() {
+        Some(Ok(gs1)) => {
+            for element in &gs1.elements {
+                info!("  ({}) {}: {}", element.ai, element.title(), element.value);
+            }
+
+            let date = match (gs1.expiry(), gs1.best_before()) {
+                (Some(expiry), _) => format!("EXP: {}", expiry),
+                (None, Some(best_before)) => format!("BB: {}", best_before),
+                (None, None) => String::new(),
+            };
+            let id = match (gs1.batch(), gs1.serial()) {
+                (Some(batch), _) => format!("LOT: {}", batch),
+                (None, Some(serial)) => format!("SN: {}", serial),
+                (None, None) => String::new(),
+            };
+
+            return vec![format!("GTIN: {}", gs1.gtin().unwrap_or("-")), date, id];
+        }
+        Some(Err(e)) => log::warn!("Invalid GS1 data in scan: {}", e),
+        None => {}
+    }
+
+    match scan.content() {
+        Ok(ScanContent::Url(link)) => vec![
+            format!("URL: {}", link.domain()),
+            String::new(),
+            String::new(),
+        ],
+        Ok(ScanContent::Wifi(network)) => vec![
+            format!("Wi-Fi: {}", network.ssid),
+            format!("Security: {}", network.security.as_str()),
+            String::new(),
+        ],
+        Ok(ScanContent::Contact(contact)) => {
+            let name = if contact.name.is_empty() {
+                &contact.organization
+            } else {
+                &contact.name
+            };
+            vec![
+                format!("Contact: {}", name),
+                contact.phones.first().cloned().unwrap_or_default(),
+                contact.emails.first().cloned().unwrap_or_default(),
+            ]
+        }
+        Ok(ScanContent::Geo(location)) => vec![
+            String::from("Location:"),
+            format!("{:.5}, {:.5}", location.latitude, location.longitude),
+            location.query.unwrap_or_default(),
+        ],
+        content => {
+            if let Err(e) = content {
+                log::warn!(
+                    "Unreadable {} content: {}",
+                    symbology_label(scan.symbology),
+                    e
+                );
+            }
+
+            vec![
+                format!("{}: {}", symbology_label(scan.symbology), scan.payload),
+                String::new(),
+                String::new(),
+            ]
+        }
+    }
+}
+
+/// Wait up to [`WIFI_PROVISIONING_TIMEOUT`] for a `WIFI:` QR code and join
+/// its network, showing progress on the display. The credentials are stored
+/// once the connection is up; returns whether it is.
+fn provision_wifi(
+    wifi: &mut BlockingWifi<EspWifi<'static>>,
+    wifi_nvs: &EspDefaultNvs,
+    barcode: &BarcodeHandle,
+    display: &DisplayHandle,
+    provisioning: &AtomicBool,
+) -> bool {
+    let show = |lines: [&str; 3]| {
+        let lines = lines.iter().map(|line| line.to_string()).collect();
+        if let Err(e) = display.submit(DisplayJob::UpdateLines {
+            first_line: 0,
+            lines,
+        }) {
+            log::error!("Failed to submit Wi-Fi setup display job: {:?}", e);
+        }
+    };
+
+    info!("Waiting for a Wi-Fi QR code");
+    show(["Wi-Fi setup", "Scan a Wi-Fi QR code", ""]);
+    provisioning.store(true, Ordering::Relaxed);
+
+    let deadline = Instant::now() + WIFI_PROVISIONING_TIMEOUT;
+    let mut connected = false;
+    while !connected && Instant::now() < deadline {
+        let network = match barcode.scan_once(WIFI_PROVISIONING_SCAN) {
+            Ok(scan) => match scan.content() {
+                Ok(ScanContent::Wifi(network)) => network,
+                Ok(_) => {
+                    show(["Wi-Fi setup", "Not a Wi-Fi QR code", ""]);
+                    continue;
+                }
+                Err(e) => {
+                    log::warn!("Unreadable Wi-Fi QR code: {}", e);
+                    show(["Wi-Fi setup", "Bad Wi-Fi QR code", ""]);
+                    continue;
+                }
+            },
+            Err(ScanError::Timeout) => continue,
+            Err(ScanError::Invalid(_) | ScanError::Binary(_)) => {
+                show(["Wi-Fi setup", "Not a Wi-Fi QR code", ""]);
+                continue;
+            }
+            Err(e) => {
+                log::warn!("Wi-Fi setup scan failed: {}", e);
+                thread::sleep(Duration::from_secs(1));
+                continue;
+            }
+        };
+
+        let credentials = WifiCredentials::from(network);
+        let ssid_line = format!("Wi-Fi: {}", credentials.ssid);
+        info!("Joining {:?}", credentials);
+        show([ssid_line.as_str(), "Connecting...", ""]);
+
+        match connect_wifi(wifi, &credentials) {
+            Ok(()) => {
+                connected = true;
+                if let Err(e) = credentials.save(wifi_nvs) {
+                    log::warn!("Failed to save Wi-Fi credentials to NVS: {:?}", e);
+                }
+                let ip = wifi
+                    .wifi()
+                    .sta_netif()
+                    .get_ip_info()
+                    .map(|info| info.ip.to_string())
+                    .unwrap_or_default();
+                info!("Joined {}, IP {}", credentials.ssid, ip);
+                show([ssid_line.as_str(), "Connected", ip.as_str()]);
+            }
+            Err(e) => {
+                log::warn!("Failed to join {}: {:?}", credentials.ssid, e);
+                show([ssid_line.as_str(), "Failed to connect", "Scan again"]);
+            }
+        }
+    }
+
+    provisioning.store(false, Ordering::Relaxed);
+    if !connected {
+        info!("Wi-Fi setup timed out");
+        show(["Wi-Fi setup", "Timed out", ""]);
+    }
+    connected
+}
+
+/// Join the stored network again, if there is one; returns whether it worked.
+fn reconnect_stored_wifi(
+    wifi: &mut BlockingWifi<EspWifi<'static>>,
+    wifi_nvs: &EspDefaultNvs,
+) -> bool {
+    let Some(credentials) = WifiCredentials::load(wifi_nvs) else {
+        return false;
+    };
+    match connect_wifi(wifi, &credentials) {
+        Ok(()) => true,
+        Err(e) => {
+            log::warn!("Failed to join {}: {:?}", credentials.ssid, e);
+            false
+        }
+    }
+}
+
+fn connect_wifi(
+    wifi: &mut BlockingWifi<EspWifi<'static>>,
+    credentials: &WifiCredentials,
+) -> anyhow::Result<()> {
+    if wifi.is_started()? && wifi.is_connected()? {
+        wifi.disconnect()?;
+    }
+    wifi.set_configuration(&credentials.configuration()?)?;
+
+    if !wifi.is_started()? {
+        wifi.start()?;
+        info!("Wi-Fi driver started");
+    }
 
     wifi.connect()?;
     info!("Wi-Fi connecting…");
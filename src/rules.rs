@@ -0,0 +1,174 @@
+//! Runs the scan-to-action rule table on the device.
+//!
+//! Scans are handed to a worker thread, so a slow webhook never holds up the
+//! scanner. The table is shared with the HTTP server, which edits it, and is
+//! stored in NVS as text.
+
+use std::sync::mpsc::Receiver;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use embedded_svc::http::client::Client;
+use embedded_svc::io::Write as _;
+use esp_idf_svc::http::client::{Configuration as HttpClientConfig, EspHttpConnection};
+use esp_idf_svc::nvs::EspDefaultNvs;
+
+pub use scanner::rules::{ActionSink, Rule, RuleEngine};
+
+use crate::barcode::{BarcodeHandle, Feedback, ScanResult, Symbology};
+use crate::epaper::{DisplayHandle, DisplayJob};
+use crate::json::json_string;
+
+/// NVS key holding the rule table.
+const NVS_KEY: &str = "rules";
+
+/// Largest string NVS stores.
+const NVS_MAX_LEN: usize = 4000;
+
+/// Lines a rule's display output covers, so it replaces a whole scan display.
+const DISPLAY_LINES: usize = 3;
+
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Load the stored rule table; an unreadable table is logged and replaced
+/// with an empty one.
+pub fn load(nvs: &EspDefaultNvs) -> RuleEngine {
+    let mut buf = vec![0u8; NVS_MAX_LEN + 1];
+    let text = match nvs.get_str(NVS_KEY, &mut buf) {
+        Ok(Some(text)) => text,
+        Ok(None) => return RuleEngine::default(),
+        Err(e) => {
+            log::warn!("Failed to read rules from NVS: {:?}", e);
+            return RuleEngine::default();
+        }
+    };
+    RuleEngine::parse(text).unwrap_or_else(|e| {
+        log::warn!("Ignoring stored rules: {:?}", e);
+        RuleEngine::default()
+    })
+}
+
+pub fn save(engine: &RuleEngine, nvs: &EspDefaultNvs) -> Result<()> {
+    let text = engine.to_string();
+    if text.len() > NVS_MAX_LEN {
+        bail!("Rules take {} bytes, at most {NVS_MAX_LEN} fit", text.len());
+    }
+    nvs.set_str(NVS_KEY, &text)?;
+    Ok(())
+}
+
+/// Carries out rule actions with the display, scanner and network.
+struct DeviceSink {
+    display: DisplayHandle,
+    barcode: BarcodeHandle,
+    /// Webhook URLs and bodies, posted once the rule table is unlocked.
+    webhooks: Vec<(String, String)>,
+}
+
+impl ActionSink for DeviceSink {
+    fn display(&mut self, mut lines: Vec<String>) {
+        if lines.len() < DISPLAY_LINES {
+            lines.resize(DISPLAY_LINES, String::new());
+        }
+        if let Err(e) = self.display.submit(DisplayJob::UpdateLines {
+            first_line: 0,
+            lines,
+        }) {
+            log::error!("Failed to submit rule display job: {:?}", e);
+        }
+    }
+
+    fn beep(&mut self, feedback: Feedback) {
+        if let Err(e) = self.barcode.play_feedback(feedback) {
+            log::warn!("Failed to play rule feedback: {:?}", e);
+        }
+    }
+
+    fn webhook(&mut self, url: &str, symbology: Option<Symbology>, payload: &str) {
+        let symbology = symbology
+            .map(|sym| json_string(sym.as_str()))
+            .unwrap_or_else(|| "null".into());
+        let body = format!(
+            r#"{{"symbology":{},"payload":{}}}"#,
+            symbology,
+            json_string(payload)
+        );
+        self.webhooks.push((url.to_string(), body));
+    }
+}
+
+/// Runs rules for each scan in a worker thread.
+pub struct RuleRunner;
+
+impl RuleRunner {
+    /// Start the rules worker.
+    ///
+    /// Scans arriving on `scans` are matched against `engine`. Scans that no
+    /// rule matches are passed to `on_unmatched`.
+    pub fn start<F>(
+        scans: Receiver<ScanResult>,
+        engine: Arc<Mutex<RuleEngine>>,
+        display: DisplayHandle,
+        barcode: BarcodeHandle,
+        mut on_unmatched: F,
+    ) -> Result<()>
+    where
+        F: 'static + Send + FnMut(&ScanResult),
+    {
+        let mut sink = DeviceSink {
+            display,
+            barcode,
+            webhooks: Vec::new(),
+        };
+
+        // Webhooks over HTTPS need room for the TLS handshake.
+        thread::Builder::new()
+            .name("rules-worker".into())
+            .stack_size(12 * 1024)
+            .spawn(move || {
+                for scan in scans {
+                    let matched = {
+                        let mut engine = engine.lock().unwrap();
+                        engine.dispatch(scan.symbology, &scan.payload, &mut sink)
+                    };
+                    match matched {
+                        Some(index) => log::info!("Scan matched rule {}", index),
+                        None => on_unmatched(&scan),
+                    }
+
+                    for (url, body) in sink.webhooks.drain(..) {
+                        match post_json(&url, &body) {
+                            Ok(status) => log::info!("Webhook {} answered {}", url, status),
+                            Err(e) => log::warn!("Webhook {} failed: {:?}", url, e),
+                        }
+                    }
+                }
+            })
+            .context("Failed to spawn rules worker thread")?;
+
+        Ok(())
+    }
+}
+
+/// POST `body` as JSON and return the response status.
+fn post_json(url: &str, body: &str) -> Result<u16> {
+    let connection = EspHttpConnection::new(&HttpClientConfig {
+        timeout: Some(WEBHOOK_TIMEOUT),
+        crt_bundle_attach: Some(esp_idf_svc::sys::esp_crt_bundle_attach),
+        ..Default::default()
+    })?;
+    let mut client = Client::wrap(connection);
+
+    let content_length = body.len().to_string();
+    let headers = [
+        ("Content-Type", "application/json"),
+        ("Content-Length", content_length.as_str()),
+    ];
+    let mut request = client.post(url, &headers)?;
+    request.write_all(body.as_bytes())?;
+    request.flush()?;
+    let response = request.submit()?;
+    Ok(response.status())
+}
@@ -0,0 +1,258 @@
+//! Bounded scan history persisted in NVS.
+//!
+//! Each entry is a small blob in its own slot key (`h0`..`h49`), written as
+//! the scan arrives, so the history survives reboots. The slot is picked
+//! from the entry's sequence number, so the oldest entry is overwritten
+//! once the history is full. Scans are handed to a writer thread, so the
+//! flash write never holds up the scanner worker.
+
+use anyhow::{Context, Result};
+use esp_idf_svc::nvs::EspDefaultNvs;
+use std::collections::VecDeque;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::barcode::Symbology;
+
+/// Entries kept; sized for the default 24 KB NVS partition, which also
+/// holds the Wi-Fi and scanner settings.
+pub const CAPACITY: usize = 50;
+
+/// Longest payload stored, in bytes; longer payloads are truncated.
+const MAX_PAYLOAD_LEN: usize = 128;
+
+/// Longest symbology name, with room to spare.
+const MAX_SYMBOLOGY_LEN: usize = 16;
+
+/// Bytes before the symbology name.
+const HEADER_LEN: usize = 15;
+
+/// Blob layout version, bumped whenever the layout changes; entries with
+/// another version are skipped.
+const FORMAT_VERSION: u8 = 1;
+
+/// How a scan ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScanOutcome {
+    /// Read and validated.
+    Ok = 0,
+    /// Failed check digit validation.
+    Invalid = 1,
+    /// Binary payload; stored as hex.
+    Binary = 2,
+}
+
+impl ScanOutcome {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScanOutcome::Ok => "ok",
+            ScanOutcome::Invalid => "invalid",
+            ScanOutcome::Binary => "binary",
+        }
+    }
+
+    fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(ScanOutcome::Ok),
+            1 => Some(ScanOutcome::Invalid),
+            2 => Some(ScanOutcome::Binary),
+            _ => None,
+        }
+    }
+}
+
+/// One recorded scan.
+#[derive(Debug, Clone)]
+pub struct HistoryEntry {
+    /// Increases by one per scan, across reboots.
+    pub seq: u32,
+    /// Seconds since the Unix epoch; small values mean the clock wasn't set
+    /// from SNTP yet.
+    pub timestamp: u64,
+    pub symbology: Option<Symbology>,
+    pub outcome: ScanOutcome,
+    pub payload: String,
+}
+
+impl HistoryEntry {
+    /// `[version][seq u32][timestamp u64][outcome][name len][name][payload]`,
+    /// little-endian, where `name` is the symbology's [`Symbology::as_str`]
+    /// name and empty if unknown.
+    fn encode(&self) -> Vec<u8> {
+        let name = self.symbology.map_or("", Symbology::as_str);
+        let mut out = Vec::with_capacity(HEADER_LEN + name.len() + self.payload.len());
+        out.push(FORMAT_VERSION);
+        out.extend_from_slice(&self.seq.to_le_bytes());
+        out.extend_from_slice(&self.timestamp.to_le_bytes());
+        out.push(self.outcome as u8);
+        out.push(name.len() as u8);
+        out.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(self.payload.as_bytes());
+        out
+    }
+
+    fn decode(blob: &[u8]) -> Option<Self> {
+        if blob.len() < HEADER_LEN || blob[0] != FORMAT_VERSION {
+            return None;
+        }
+        let (header, rest) = blob.split_at(HEADER_LEN);
+        let name_len = header[14] as usize;
+        if rest.len() < name_len {
+            return None;
+        }
+        let (name, payload) = rest.split_at(name_len);
+        let symbology = match name {
+            [] => None,
+            name => Some(std::str::from_utf8(name).ok()?.parse().ok()?),
+        };
+        Some(HistoryEntry {
+            seq: u32::from_le_bytes(header[1..5].try_into().ok()?),
+            timestamp: u64::from_le_bytes(header[5..13].try_into().ok()?),
+            symbology,
+            outcome: ScanOutcome::from_u8(header[13])?,
+            payload: String::from_utf8_lossy(payload).into_owned(),
+        })
+    }
+}
+
+/// The stored scan history, cached in memory.
+pub struct ScanHistory {
+    nvs: EspDefaultNvs,
+    /// Oldest first.
+    entries: VecDeque<HistoryEntry>,
+    next_seq: u32,
+}
+
+impl ScanHistory {
+    pub fn load(nvs: EspDefaultNvs) -> Self {
+        let mut buf = [0u8; HEADER_LEN + MAX_SYMBOLOGY_LEN + MAX_PAYLOAD_LEN];
+        let mut entries: Vec<HistoryEntry> = (0..CAPACITY)
+            .filter_map(|slot| {
+                let blob = nvs.get_blob(&slot_key(slot), &mut buf).ok().flatten()?;
+                HistoryEntry::decode(blob)
+            })
+            .collect();
+        entries.sort_by_key(|entry| entry.seq);
+
+        let next_seq = entries.last().map_or(0, |entry| entry.seq.wrapping_add(1));
+        ScanHistory {
+            nvs,
+            entries: entries.into(),
+            next_seq,
+        }
+    }
+
+    /// Append a scan, replacing the oldest entry once full.
+    pub fn record(
+        &mut self,
+        symbology: Option<Symbology>,
+        outcome: ScanOutcome,
+        payload: &str,
+        time: SystemTime,
+    ) -> Result<()> {
+        let entry = HistoryEntry {
+            seq: self.next_seq,
+            timestamp: time
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |since| since.as_secs()),
+            symbology,
+            outcome,
+            payload: truncate(payload, MAX_PAYLOAD_LEN).to_string(),
+        };
+        self.next_seq = self.next_seq.wrapping_add(1);
+
+        self.nvs
+            .set_blob(&slot_key(entry.seq as usize % CAPACITY), &entry.encode())?;
+
+        if self.entries.len() == CAPACITY {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+        Ok(())
+    }
+
+    /// Number of stored entries.
+    pub fn count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Entries newest first, skipping `offset` and returning at most `limit`.
+    pub fn page(&self, offset: usize, limit: usize) -> impl Iterator<Item = &HistoryEntry> {
+        self.entries.iter().rev().skip(offset).take(limit)
+    }
+}
+
+/// A scan waiting to be written.
+struct PendingEntry {
+    symbology: Option<Symbology>,
+    outcome: ScanOutcome,
+    payload: String,
+    time: SystemTime,
+}
+
+/// Hands scans to the history writer thread.
+pub struct HistoryRecorder {
+    tx: Sender<PendingEntry>,
+}
+
+impl HistoryRecorder {
+    /// Start the writer thread, which records each scan in `history`.
+    pub fn start(history: Arc<Mutex<ScanHistory>>) -> Result<Self> {
+        let (tx, rx) = mpsc::channel();
+
+        // NVS writes need more than a 4 KB stack.
+        thread::Builder::new()
+            .name("scan-history".into())
+            .stack_size(8192)
+            .spawn(move || {
+                for entry in rx {
+                    let result = history.lock().unwrap().record(
+                        entry.symbology,
+                        entry.outcome,
+                        &entry.payload,
+                        entry.time,
+                    );
+                    if let Err(e) = result {
+                        log::warn!("Failed to record scan history: {:?}", e);
+                    }
+                }
+            })
+            .context("Failed to spawn scan history thread")?;
+
+        Ok(HistoryRecorder { tx })
+    }
+
+    /// Queue a scan for recording; returns at once.
+    pub fn record(
+        &self,
+        symbology: Option<Symbology>,
+        outcome: ScanOutcome,
+        payload: &str,
+        time: SystemTime,
+    ) {
+        let entry = PendingEntry {
+            symbology,
+            outcome,
+            payload: truncate(payload, MAX_PAYLOAD_LEN).to_string(),
+            time,
+        };
+        if self.tx.send(entry).is_err() {
+            log::warn!("Scan history thread has stopped");
+        }
+    }
+}
+
+fn slot_key(slot: usize) -> String {
+    format!("h{slot}")
+}
+
+/// Cut `s` to at most `max` bytes on a character boundary.
+fn truncate(s: &str, max: usize) -> &str {
+    let mut end = s.len().min(max);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
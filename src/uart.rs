@@ -0,0 +1,162 @@
+//! [`ScannerPort`] for the ESP-IDF UART driver.
+//!
+//! Receiving is driven by the driver's event queue: the reader thread sleeps
+//! until the UART interrupt reports data, then reads the whole burst. With a
+//! terminator set, the UART's pattern detection ends a burst at the end of
+//! each scan, so a long 2D payload arrives as a single read.
+
+use std::time::Duration;
+
+use anyhow::{bail, Context, Result};
+use esp_idf_hal::delay::{TickType, NON_BLOCK};
+use esp_idf_hal::io::{ErrorType, EspIOError, Write};
+use esp_idf_hal::sys::{
+    esp, uart_disable_pattern_det_intr, uart_enable_pattern_det_baud_intr, uart_flush_input,
+    uart_get_baudrate, uart_get_buffered_data_len, uart_pattern_pop_pos, uart_pattern_queue_reset,
+    uart_port_t, uart_set_baudrate,
+};
+use esp_idf_hal::uart::{UartDriver, UartEventPayload, UartRxDriver, UartTxDriver};
+
+use scanner::barcode::{ScannerPort, ScannerRx};
+
+/// Terminator positions the driver remembers between reads.
+const PATTERN_QUEUE_LEN: i32 = 16;
+/// Baud cycles of idle line after which a detected terminator counts.
+const PATTERN_TIMEOUT: i32 = 9;
+
+/// The scanner UART, split so the worker can write while a reader thread
+/// waits on the event queue.
+///
+/// The driver must be created with an event queue (`queue_size` in its
+/// config), which the reader waits on.
+pub struct UartPort {
+    port: uart_port_t,
+    tx: UartTxDriver<'static>,
+    rx: Option<UartRxDriver<'static>>,
+}
+
+impl UartPort {
+    pub fn new(uart: UartDriver<'static>) -> Self {
+        let port = uart.port();
+        let (tx, rx) = uart.into_split();
+        Self {
+            port,
+            tx,
+            rx: Some(rx),
+        }
+    }
+}
+
+impl ErrorType for UartPort {
+    type Error = EspIOError;
+}
+
+impl Write for UartPort {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, EspIOError> {
+        Ok(UartTxDriver::write(&mut self.tx, buf)?)
+    }
+
+    fn flush(&mut self) -> Result<(), EspIOError> {
+        Ok(())
+    }
+}
+
+impl ScannerPort for UartPort {
+    type Rx = UartRx;
+
+    fn take_rx(&mut self) -> Result<UartRx> {
+        let driver = self.rx.take().context("UART receive side already taken")?;
+        driver
+            .event_queue()
+            .context("UART driver was created without an event queue")?;
+        Ok(UartRx {
+            port: self.port,
+            driver,
+        })
+    }
+
+    fn baud_rate(&self) -> Result<u32> {
+        let mut bps = 0;
+        esp!(unsafe { uart_get_baudrate(self.port, &mut bps) })?;
+        Ok(bps)
+    }
+
+    fn set_baud_rate(&mut self, bps: u32) -> Result<()> {
+        esp!(unsafe { uart_set_baudrate(self.port, bps) })?;
+        Ok(())
+    }
+
+    fn clear_rx(&mut self) -> Result<()> {
+        esp!(unsafe { uart_flush_input(self.port) })?;
+        Ok(())
+    }
+
+    fn set_terminator(&mut self, byte: Option<u8>) -> Result<()> {
+        match byte {
+            Some(byte) => {
+                esp!(unsafe {
+                    uart_enable_pattern_det_baud_intr(
+                        self.port,
+                        byte as _,
+                        1,
+                        PATTERN_TIMEOUT,
+                        0,
+                        0,
+                    )
+                })?;
+                esp!(unsafe { uart_pattern_queue_reset(self.port, PATTERN_QUEUE_LEN) })?;
+            }
+            None => esp!(unsafe { uart_disable_pattern_det_intr(self.port) })?,
+        }
+        Ok(())
+    }
+}
+
+/// The receive side of [`UartPort`].
+pub struct UartRx {
+    port: uart_port_t,
+    driver: UartRxDriver<'static>,
+}
+
+impl UartRx {
+    fn buffered_len(&self) -> Result<usize> {
+        let mut len = 0;
+        esp!(unsafe { uart_get_buffered_data_len(self.port, &mut len) })?;
+        Ok(len)
+    }
+}
+
+impl ScannerRx for UartRx {
+    fn read_burst(&mut self, buf: &mut [u8], timeout: Duration) -> Result<usize> {
+        let queue = self
+            .driver
+            .event_queue()
+            .context("UART event queue missing")?;
+        let ticks = TickType::new_millis(timeout.as_millis() as u64).ticks();
+        let Some((event, _)) = queue.recv_front(ticks) else {
+            return Ok(0);
+        };
+
+        let len = match event.payload() {
+            UartEventPayload::PatternDetected => {
+                // Read up to and including the terminator. The position is
+                // -1 if the pattern queue overflowed; then take everything.
+                match unsafe { uart_pattern_pop_pos(self.port) } {
+                    pos if pos >= 0 => pos as usize + 1,
+                    _ => self.buffered_len()?,
+                }
+            }
+            UartEventPayload::FifoOverflow | UartEventPayload::BufferFull => {
+                esp!(unsafe { uart_flush_input(self.port) })?;
+                bail!("UART receive buffer overflowed");
+            }
+            _ => self.buffered_len()?,
+        };
+        if len == 0 {
+            return Ok(0);
+        }
+
+        let len = len.min(buf.len());
+        Ok(self.driver.read(&mut buf[..len], NON_BLOCK)?)
+    }
+}
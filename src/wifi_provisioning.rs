@@ -0,0 +1,164 @@
+//! Wi-Fi credentials kept in NVS, set by scanning a `WIFI:` QR code.
+//!
+//! The device enters provisioning when it has no stored network or when the
+//! provisioning button is held; `main` then waits for a Wi-Fi code from the
+//! scanner and connects to it.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Result};
+use embedded_svc::wifi::{AuthMethod, ClientConfiguration, Configuration};
+use esp_idf_hal::gpio::{Input, Pin, PinDriver};
+use esp_idf_svc::nvs::EspDefaultNvs;
+
+use crate::barcode::{WifiNetwork, WifiSecurity};
+
+/// How long the button must be held to start provisioning.
+pub const LONG_PRESS: Duration = Duration::from_secs(3);
+
+const BUTTON_POLL: Duration = Duration::from_millis(50);
+
+/// Longest SSID and password the ESP-IDF station config holds, in bytes.
+const MAX_SSID_LEN: usize = 32;
+const MAX_PASSWORD_LEN: usize = 64;
+
+/// A network to join, as stored in the `wifi` NVS namespace.
+#[derive(Clone)]
+pub struct WifiCredentials {
+    pub ssid: String,
+    pub password: String,
+    pub security: WifiSecurity,
+}
+
+impl WifiCredentials {
+    /// The stored network, if one was provisioned.
+    pub fn load(nvs: &EspDefaultNvs) -> Option<Self> {
+        let mut ssid = [0u8; MAX_SSID_LEN + 1];
+        let mut password = [0u8; MAX_PASSWORD_LEN + 1];
+
+        let ssid = nvs.get_str("ssid", &mut ssid).ok().flatten()?.to_string();
+        let password = nvs
+            .get_str("password", &mut password)
+            .ok()
+            .flatten()
+            .unwrap_or("")
+            .to_string();
+        let security = nvs
+            .get_u8("security")
+            .ok()
+            .flatten()
+            .map(security_from_code)
+            .unwrap_or(WifiSecurity::Wpa);
+
+        Some(WifiCredentials {
+            ssid,
+            password,
+            security,
+        })
+    }
+
+    pub fn save(&self, nvs: &EspDefaultNvs) -> Result<()> {
+        nvs.set_str("ssid", &self.ssid)?;
+        nvs.set_str("password", &self.password)?;
+        nvs.set_u8("security", security_code(self.security))?;
+        Ok(())
+    }
+
+    /// Station configuration for joining this network.
+    pub fn configuration(&self) -> Result<Configuration> {
+        let auth_method = match self.security {
+            WifiSecurity::Open => AuthMethod::None,
+            WifiSecurity::Wep => AuthMethod::WEP,
+            WifiSecurity::Wpa => AuthMethod::WPAWPA2Personal,
+            WifiSecurity::Sae => AuthMethod::WPA3Personal,
+        };
+
+        Ok(Configuration::Client(ClientConfiguration {
+            ssid: self
+                .ssid
+                .as_str()
+                .try_into()
+                .map_err(|_| anyhow!("SSID is longer than {MAX_SSID_LEN} bytes"))?,
+            bssid: None,
+            auth_method,
+            password: self
+                .password
+                .as_str()
+                .try_into()
+                .map_err(|_| anyhow!("Password is longer than {MAX_PASSWORD_LEN} bytes"))?,
+            channel: None,
+            ..Default::default()
+        }))
+    }
+}
+
+impl From<WifiNetwork> for WifiCredentials {
+    fn from(network: WifiNetwork) -> Self {
+        WifiCredentials {
+            ssid: network.ssid,
+            password: network.password,
+            security: network.security,
+        }
+    }
+}
+
+// Keep the password out of logs.
+impl fmt::Debug for WifiCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WifiCredentials")
+            .field("ssid", &self.ssid)
+            .field("security", &self.security)
+            .finish_non_exhaustive()
+    }
+}
+
+fn security_code(security: WifiSecurity) -> u8 {
+    match security {
+        WifiSecurity::Open => 0,
+        WifiSecurity::Wep => 1,
+        WifiSecurity::Wpa => 2,
+        WifiSecurity::Sae => 3,
+    }
+}
+
+fn security_from_code(code: u8) -> WifiSecurity {
+    match code {
+        0 => WifiSecurity::Open,
+        1 => WifiSecurity::Wep,
+        3 => WifiSecurity::Sae,
+        _ => WifiSecurity::Wpa,
+    }
+}
+
+/// Call `on_long_press` each time the active-low `button` is held for
+/// [`LONG_PRESS`]. Polls on its own thread.
+pub fn watch_button<P, F>(button: PinDriver<'static, P, Input>, mut on_long_press: F) -> Result<()>
+where
+    P: Pin,
+    F: FnMut() + Send + 'static,
+{
+    std::thread::Builder::new()
+        .name("wifi-button".into())
+        .stack_size(3072)
+        .spawn(move || {
+            let mut pressed_since: Option<Instant> = None;
+            let mut fired = false;
+
+            loop {
+                if button.is_low() {
+                    let since = *pressed_since.get_or_insert_with(Instant::now);
+                    // Fire once per press, while still held.
+                    if !fired && since.elapsed() >= LONG_PRESS {
+                        fired = true;
+                        on_long_press();
+                    }
+                } else {
+                    pressed_since = None;
+                    fired = false;
+                }
+                std::thread::sleep(BUTTON_POLL);
+            }
+        })?;
+    Ok(())
+}